@@ -1,13 +1,46 @@
 use eframe::egui;
+use eframe::egui::text::{LayoutJob, TextFormat};
 use eframe::egui::{Button, RichText, Slider, TextEdit, TextStyle};
 use harptabber::Style;
 use std::collections::BTreeMap;
-
-#[cfg(not(target_arch = "wasm32"))]
-use rodio::{OutputStream, Sink};
+use std::ops::Range;
+use std::sync::Arc;
+
+use harptabber::{
+    AnswerKind, AudioOutput, BackingOptions, BendLimits, CheckedNote, HarpTab, HoleRange, Holes,
+    InputFormat, Intonation, Metronome, PerformanceCheck, PlatformOutput, PlaybackEvent,
+    PlaybackNote, Player, PracticeOptions, PracticeSession, Progression, QuestionKind, QuizOptions,
+    QuizSession, RangeBound, ReedOffsets, TabHeader, TechniqueInventory, TechniqueProfile,
+    Temperament, TimeSignature, TuningPosition,
+};
+use std::str::FromStr;
+
+const TUNINGS: [&str; 18] = [
+    "richter",
+    "paddy richter",
+    "country",
+    "natural minor",
+    "harmonic minor",
+    "wilde tuning",
+    "wilde minor tuning",
+    "pentaharp",
+    "powerbender",
+    "powerdraw",
+    "diminished",
+    "spiral",
+    "lucky 13 diminished",
+    "lucky 13 powerchromatic",
+    "melody maker",
+    "easy 3rd",
+    "4 hole richter",
+    "5 hole richter",
+];
 
 pub struct GUIApp {
     input_text: String,
+    /// what the input box is written in, note names and degrees are converted into `input_text`
+    input_format: InputFormat,
+    melody_text: String,
     output_text: String,
     semitone_shift: i32,
     from_position: u32,
@@ -23,18 +56,56 @@ pub struct GUIApp {
     key: &'static str,
     notes_in_order: Vec<String>,
     duplicated_notes: Vec<String>,
-    playable_without_overblows: Vec<(u32, i32)>,
-    playable_without_bends: Vec<(u32, i32)>,
-    allow_bends: bool,
+    playable_positions: Vec<(u32, i32)>,
+    all_tunings: bool,
+    playable_in_all_tunings: Vec<TuningPosition>,
+    technique_choice: TechniqueChoice,
+    custom_technique: TechniqueProfile,
+    split_octaves: bool,
+    limit_holes: bool,
+    lowest_hole: u32,
+    lowest_note: BoundNote,
+    highest_hole: u32,
+    highest_note: BoundNote,
     keep_errors: bool,
 
     error_text: String,
     about_open: bool,
     help_open: bool,
 
-    #[cfg(not(target_arch = "wasm32"))]
-    audio_context: AudioContext,
+    player: Player<PlatformOutput>,
     should_play_note: bool,
+    playback_source: TextSource,
+    playing_span: Option<Range<usize>>,
+    playing_hole: Option<String>,
+    loop_playback: bool,
+    selection: Option<(TextSource, Range<usize>)>,
+    practice_options: PracticeOptions,
+    practice: Option<PracticeSession>,
+    loop_had_mistake: bool,
+    backing_options: BackingOptions,
+    progression_text: String,
+    backing_with_tab: bool,
+    backing_error: String,
+    metronome: Metronome,
+    metronome_enabled: bool,
+    temperament: Temperament,
+    reference_pitch: f32,
+    reed_offsets_text: String,
+    reed_offsets_error: String,
+    check_path: String,
+    check: Option<PerformanceCheck>,
+    check_error: String,
+    quiz_question: QuestionKind,
+    quiz_answer: AnswerKind,
+    quiz_scale: Option<&'static str>,
+    quiz: Option<QuizSession>,
+    quiz_guess: String,
+    quiz_feedback: String,
+    quizzes_started: u64,
+    tab_path: String,
+    tab_header: TabHeader,
+    tab_file_message: String,
 
     scales: &'static BTreeMap<String, Vec<&'static str>>,
     selected_scale: Option<&'static str>,
@@ -47,32 +118,45 @@ enum DisplayOption {
     Notes,
 }
 
-#[cfg(not(target_arch = "wasm32"))]
-struct AudioContext {
-    _output_stream: rodio::OutputStream,
-    _stream_handle: rodio::OutputStreamHandle,
-    sink: rodio::Sink,
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum TechniqueChoice {
+    WithoutOverblows,
+    WithoutBends,
+    Custom,
 }
 
-#[cfg(not(target_arch = "wasm32"))]
-impl AudioContext {
-    fn new() -> Self {
-        let (_output_stream, _stream_handle) = OutputStream::try_default().unwrap();
-        let sink = Sink::try_new(&_stream_handle).unwrap();
-        Self {
-            _output_stream,
-            _stream_handle,
-            sink,
+/// which notes of its hole an end of the hole range allows
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum BoundNote {
+    AnyNote,
+    Blow,
+    Draw,
+}
+
+impl BoundNote {
+    fn bound(self, hole: u32) -> RangeBound {
+        match self {
+            BoundNote::AnyNote => RangeBound::Hole(hole),
+            BoundNote::Blow => RangeBound::Note(hole.to_string()),
+            BoundNote::Draw => RangeBound::Note(format!("-{}", hole)),
         }
     }
 }
 
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum TextSource {
+    Input,
+    Output,
+}
+
 impl Default for GUIApp {
     fn default() -> Self {
         let (notes, duplicated) = harptabber::tuning_to_notes_in_order("richter");
 
         Self {
             input_text: String::new(),
+            input_format: InputFormat::Tab,
+            melody_text: String::new(),
             output_text: String::new(),
             semitone_shift: 0,
             from_position: 1,
@@ -88,18 +172,66 @@ impl Default for GUIApp {
             key: "C",
             notes_in_order: notes,
             duplicated_notes: duplicated,
-            playable_without_overblows: Vec::new(),
-            playable_without_bends: Vec::new(),
-            allow_bends: true,
+            playable_positions: Vec::new(),
+            all_tunings: false,
+            playable_in_all_tunings: Vec::new(),
+            technique_choice: TechniqueChoice::WithoutOverblows,
+            custom_technique: TechniqueProfile {
+                draw_bends: BendLimits {
+                    depth: 3,
+                    holes: BTreeMap::new(),
+                },
+                blow_bends: BendLimits {
+                    depth: 2,
+                    holes: BTreeMap::new(),
+                },
+                ..TechniqueProfile::none()
+            },
+            split_octaves: false,
+            limit_holes: false,
+            lowest_hole: 4,
+            lowest_note: BoundNote::AnyNote,
+            highest_hole: 7,
+            highest_note: BoundNote::AnyNote,
             keep_errors: false,
 
             error_text: String::new(),
             about_open: false,
             help_open: false,
 
-            #[cfg(not(target_arch = "wasm32"))]
-            audio_context: AudioContext::new(),
+            player: Player::new(PlatformOutput::new()),
             should_play_note: false,
+            playback_source: TextSource::Input,
+            playing_span: None,
+            playing_hole: None,
+            loop_playback: false,
+            selection: None,
+            practice_options: PracticeOptions::default(),
+            practice: None,
+            loop_had_mistake: false,
+            backing_options: BackingOptions::default(),
+            progression_text: String::from("I IV V IV"),
+            backing_with_tab: true,
+            backing_error: String::new(),
+            metronome: Metronome::default(),
+            metronome_enabled: false,
+            temperament: Temperament::Equal,
+            reference_pitch: 440.0,
+            reed_offsets_text: String::new(),
+            reed_offsets_error: String::new(),
+            check_path: String::new(),
+            check: None,
+            check_error: String::new(),
+            quiz_question: QuestionKind::Hole,
+            quiz_answer: AnswerKind::Hole,
+            quiz_scale: None,
+            quiz: None,
+            quiz_guess: String::new(),
+            quiz_feedback: String::new(),
+            quizzes_started: 0,
+            tab_path: String::new(),
+            tab_header: TabHeader::default(),
+            tab_file_message: String::new(),
 
             scales: harptabber::get_scales(),
             selected_scale: None,
@@ -118,6 +250,8 @@ impl GUIApp {
 impl eframe::App for GUIApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         // catppuccin_egui::set_theme(ctx, catppuccin_egui::FRAPPE);
+        self.update_playback(ctx);
+
         egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
             egui::MenuBar::new().ui(ui, |ui| {
                 // NOTE: no File->Quit on web pages!
@@ -164,47 +298,944 @@ impl eframe::App for GUIApp {
 
 impl GUIApp {
     fn transpose(&mut self) {
-        let (tabs, errors) = harptabber::transpose_tabs(
-            self.input_text.clone(),
+        let (tab, input_errors) = harptabber::input_to_tab(
+            &self.melody_text,
+            self.input_format,
+            self.input_tuning,
+            self.style,
+            self.key,
+            self.from_position,
+        );
+        if self.input_format != InputFormat::Tab {
+            self.input_text = tab;
+        }
+        let (tabs, errors) = harptabber::transpose_body(
+            &self.input_text,
             self.semitone_shift,
+            self.from_position,
             self.keep_errors,
             self.style,
             self.input_tuning,
             self.output_tuning,
         );
         self.output_text = tabs;
-        self.error_text = errors.join(" ");
+        self.error_text = input_errors
+            .iter()
+            .chain(errors.iter())
+            .cloned()
+            .collect::<Vec<String>>()
+            .join(" ");
+        if self.split_octaves {
+            match harptabber::split_octaves(
+                &self.input_text,
+                self.from_position,
+                self.to_position,
+                self.input_tuning,
+                self.output_tuning,
+                self.style,
+                &self.technique(),
+            ) {
+                Ok(split) => self.output_text = split.tab(&self.input_text),
+                Err(lines) => {
+                    let lines: Vec<String> = lines.iter().map(|l| l.to_string()).collect();
+                    self.error_text = format!(
+                        "line {} can't be played in {} position in any octave",
+                        lines.join(", "),
+                        harptabber::to_ordinal(self.to_position)
+                    );
+                }
+            }
+        }
 
-        self.playable_without_overblows = harptabber::get_playable_positions(
+        self.playable_positions = harptabber::get_playable_positions_with_technique(
             &self.input_text,
             self.from_position,
             self.input_tuning,
             self.output_tuning,
             self.style,
-            true,
+            &self.technique(),
         );
-        self.playable_without_bends = harptabber::get_playable_positions(
-            &self.input_text,
+        if let Some(range) = self.hole_range() {
+            self.playable_positions = harptabber::filter_positions_by_range(
+                &self.playable_positions,
+                &self.input_text,
+                &range,
+                self.style,
+                self.input_tuning,
+                self.output_tuning,
+            );
+        }
+        if self.all_tunings {
+            self.playable_in_all_tunings = harptabber::get_playable_positions_in_all_tunings(
+                &self.input_text,
+                self.from_position,
+                self.input_tuning,
+                self.style,
+                &self.technique(),
+                self.hole_range().as_ref(),
+            );
+        }
+        self.generate_keyboard_text();
+    }
+
+    fn technique(&self) -> TechniqueProfile {
+        match self.technique_choice {
+            TechniqueChoice::WithoutOverblows => TechniqueProfile::bends(),
+            TechniqueChoice::WithoutBends => TechniqueProfile::none(),
+            TechniqueChoice::Custom => self.custom_technique.clone(),
+        }
+    }
+
+    fn technique_panel(&mut self, ui: &mut egui::Ui) {
+        let technique = &mut self.custom_technique;
+        let mut changed = ui
+            .add(Slider::new(&mut technique.draw_bends.depth, 0..=3).text("draw bend depth"))
+            .changed();
+        changed |= ui
+            .add(Slider::new(&mut technique.blow_bends.depth, 0..=2).text("blow bend depth"))
+            .changed();
+        let mut overblows = technique.overblows == Holes::All;
+        if ui.checkbox(&mut overblows, "overblows").changed() {
+            technique.overblows = if overblows { Holes::All } else { Holes::None };
+            changed = true;
+        }
+        let mut overdraws = technique.overdraws == Holes::All;
+        if ui.checkbox(&mut overdraws, "overdraws").changed() {
+            technique.overdraws = if overdraws { Holes::All } else { Holes::None };
+            changed = true;
+        }
+        changed |= ui
+            .checkbox(&mut technique.valved_bends, "valved bends")
+            .on_hover_text("a half-valved harp, playing overblow and overdraw notes as bends")
+            .changed();
+        if changed {
+            self.transpose();
+        }
+    }
+
+    fn selected_hole_range(&self) -> HoleRange {
+        HoleRange {
+            low: Some(self.lowest_note.bound(self.lowest_hole)),
+            high: Some(self.highest_note.bound(self.highest_hole)),
+        }
+    }
+
+    fn hole_range(&self) -> Option<HoleRange> {
+        self.limit_holes.then_some(self.selected_hole_range())
+    }
+
+    fn hole_range_panel(&mut self, ui: &mut egui::Ui) {
+        let mut changed = ui
+            .checkbox(
+                &mut self.limit_holes,
+                "only show positions within these holes",
+            )
+            .changed();
+        let holes = harptabber::hole_count(self.output_tuning);
+        self.lowest_hole = self.lowest_hole.min(holes);
+        self.highest_hole = self.highest_hole.min(holes);
+        changed |= ui
+            .add(Slider::new(&mut self.lowest_hole, 1..=holes).text("lowest hole"))
+            .changed();
+        changed |= bound_note_buttons(ui, &mut self.lowest_note);
+        changed |= ui
+            .add(Slider::new(&mut self.highest_hole, 1..=holes).text("highest hole"))
+            .changed();
+        changed |= bound_note_buttons(ui, &mut self.highest_note);
+        if changed {
+            self.highest_hole = self.highest_hole.max(self.lowest_hole);
+            self.transpose();
+        }
+
+        if ui.button("pick octave").clicked() {
+            let range = self.selected_hole_range();
+            match harptabber::fit_octave(
+                &self.input_text,
+                self.semitone_shift,
+                &range,
+                self.style,
+                self.input_tuning,
+                self.output_tuning,
+            ) {
+                Some(semitones) => {
+                    self.semitone_shift = semitones;
+                    self.transpose();
+                }
+                None => {
+                    self.error_text = String::from("the tab doesn't fit these holes in any octave")
+                }
+            }
+        }
+    }
+
+    /// the shift of every input line changed by directives, empty if there are none
+    fn shift_margin(&self) -> String {
+        let shifts = harptabber::line_shifts(&self.input_text);
+        if shifts
+            .iter()
+            .all(|shift| *shift == harptabber::LineShift::default())
+        {
+            return String::new();
+        }
+        let lines: Vec<String> = shifts
+            .iter()
+            .map(|shift| {
+                let total = shift.total(self.semitone_shift, self.from_position);
+                if shift.position.is_none() && total == self.semitone_shift {
+                    return String::new();
+                }
+                let position = harptabber::semitones_to_position(self.from_position, total);
+                format!(
+                    "{:+} {}",
+                    total - self.semitone_shift,
+                    harptabber::to_ordinal(position)
+                )
+            })
+            .collect();
+        lines.join("\n")
+    }
+
+    /// the span of the note being played, if it belongs to the given text
+    fn highlighted_span(&self, source: TextSource) -> Option<Range<usize>> {
+        if self.playback_source == source {
+            self.playing_span.clone()
+        } else {
+            None
+        }
+    }
+
+    fn update_playback(&mut self, ctx: &egui::Context) {
+        for event in self.player.update() {
+            match event {
+                PlaybackEvent::NoteStart { span, .. } => {
+                    let (text, tuning) = match self.playback_source {
+                        TextSource::Input => (&self.input_text, self.input_tuning),
+                        TextSource::Output => (&self.output_text, self.output_tuning),
+                    };
+                    // the tab keyboard always shows the input harp
+                    self.playing_hole = text
+                        .get(span.clone())
+                        .filter(|_| tuning == self.input_tuning)
+                        .map(|hole| hole.to_owned());
+                    self.playing_span = Some(span);
+                }
+                PlaybackEvent::NoteEnd { .. } => {
+                    self.playing_span = None;
+                    self.playing_hole = None;
+                }
+                PlaybackEvent::LoopEnd => {
+                    if let Some(session) = self.practice.as_mut() {
+                        if session.loop_finished(!self.loop_had_mistake) {
+                            self.player.set_note_duration(session.note_duration());
+                        }
+                        self.loop_had_mistake = false;
+                    }
+                }
+                PlaybackEvent::Finished => {
+                    self.playing_span = None;
+                    self.playing_hole = None;
+                    self.practice = None;
+                }
+                PlaybackEvent::CountIn { .. } => (),
+            }
+        }
+        if self.player.is_playing() {
+            ctx.request_repaint_after(std::time::Duration::from_millis(10));
+        }
+    }
+
+    fn start_playback(&mut self, source: TextSource) {
+        let (text, tuning) = match source {
+            TextSource::Input => (&self.input_text, self.input_tuning),
+            TextSource::Output => (&self.output_text, self.output_tuning),
+        };
+        let notes = harptabber::get_playback_notes_in_key(text, tuning, self.style, self.key);
+        self.apply_intonation(tuning);
+        self.player.set_notes(notes);
+        self.player.set_looping(self.loop_playback);
+        self.player.set_count_in(0);
+        self.player.set_note_duration(harptabber::NOTE_DURATION);
+        self.player.set_metronome(self.enabled_metronome());
+        self.playback_source = source;
+        self.practice = None;
+        self.player.play();
+    }
+
+    /// loop the selected notes, speeding up after clean loops
+    fn start_practice(&mut self) {
+        let Some((source, selection)) = self.selection.clone() else {
+            return;
+        };
+        let (text, tuning) = match source {
+            TextSource::Input => (&self.input_text, self.input_tuning),
+            TextSource::Output => (&self.output_text, self.output_tuning),
+        };
+        let notes = harptabber::get_playback_notes_in_key(text, tuning, self.style, self.key);
+        let selected = notes
+            .iter()
+            .enumerate()
+            .filter(|(_, note)| note.span.start < selection.end && selection.start < note.span.end)
+            .map(|(i, _)| i);
+        let (Some(first), Some(last)) = (selected.clone().min(), selected.max()) else {
+            return;
+        };
+
+        let session = PracticeSession::new(self.practice_options);
+        self.apply_intonation(tuning);
+        self.player.set_notes(notes);
+        self.player.set_section(Some(first..last + 1));
+        self.player.set_looping(true);
+        self.player.set_count_in(self.practice_options.count_in);
+        self.player.set_metronome(self.enabled_metronome());
+        self.player.set_note_duration(session.note_duration());
+        self.playback_source = source;
+        self.practice = Some(session);
+        self.loop_had_mistake = false;
+        self.player.play();
+    }
+
+    fn backing_track_panel(&mut self, ui: &mut egui::Ui) {
+        ui.label(format!(
+            "song key: {} ({} harp, {} position)",
+            harptabber::song_key(self.key, self.from_position),
+            self.key,
+            harptabber::to_ordinal(self.from_position)
+        ));
+
+        ui.horizontal(|ui| {
+            let progressions = [
+                (Progression::Drone, "drone"),
+                (Progression::TwelveBar, "12-bar"),
+                (Progression::QuickChange, "quick change"),
+            ];
+            let options = &mut self.backing_options;
+            for (progression, name) in progressions {
+                ui.selectable_value(&mut options.progression, progression, name);
+            }
+            let is_custom = matches!(options.progression, Progression::Custom(_));
+            if ui.selectable_label(is_custom, "custom").clicked() {
+                options.progression = Progression::Custom(Vec::new());
+            }
+        });
+        if matches!(self.backing_options.progression, Progression::Custom(_)) {
+            ui.add(
+                TextEdit::singleline(&mut self.progression_text)
+                    .hint_text("roman numerals, e.g. I vi IV V"),
+            );
+        }
+
+        ui.add(Slider::new(&mut self.backing_options.bpm, 20.0..=300.0).text("tempo"));
+        ui.add(
+            Slider::new(&mut self.backing_options.beats_per_chord, 1..=8).text("beats per chord"),
+        );
+        ui.checkbox(&mut self.backing_with_tab, "play input tab along");
+
+        ui.horizontal(|ui| {
+            if ui.button("play backing track").clicked() {
+                self.play_backing_track();
+            }
+            if ui.button("stop").clicked() {
+                self.player.stop();
+                self.player.output_mut().stop();
+            }
+        });
+        if !self.backing_error.is_empty() {
+            ui.label(&self.backing_error);
+        }
+    }
+
+    fn play_backing_track(&mut self) {
+        let mut options = self.backing_options.clone();
+        if matches!(options.progression, Progression::Custom(_)) {
+            match Progression::from_str(&self.progression_text) {
+                Ok(progression) => options.progression = progression,
+                Err(e) => {
+                    self.backing_error = e.to_string();
+                    return;
+                }
+            }
+        }
+        self.backing_error.clear();
+
+        let melody = if self.backing_with_tab {
+            harptabber::get_playback_notes_in_key(
+                &self.input_text,
+                self.input_tuning,
+                self.style,
+                self.key,
+            )
+        } else {
+            Vec::new()
+        };
+        let metronome = self.enabled_metronome().map(|metronome| Metronome {
+            bpm: options.bpm,
+            ..metronome
+        });
+        let samples = harptabber::render_backing_track(
+            &options,
+            self.key,
             self.from_position,
-            self.input_tuning,
-            self.output_tuning,
-            self.style,
-            false,
+            &melody,
+            metronome.as_ref(),
+            &self.intonation(self.input_tuning),
         );
-        self.generate_keyboard_text();
+
+        self.player.stop();
+        self.player.output_mut().stop();
+        self.player.output_mut().play_samples(&samples);
     }
 
-    fn playable_positions_panel(&mut self, ui: &mut egui::Ui) {
-        let pairs: &[(u32, i32)] = if self.allow_bends {
-            self.playable_without_overblows.as_ref()
+    fn enabled_metronome(&self) -> Option<Metronome> {
+        self.metronome_enabled.then_some(self.metronome)
+    }
+
+    fn metronome_panel(&mut self, ui: &mut egui::Ui) {
+        ui.checkbox(&mut self.metronome_enabled, "click along with playback");
+        ui.add(Slider::new(&mut self.metronome.bpm, 20.0..=300.0).text("tempo"));
+        ui.horizontal(|ui| {
+            for signature in ["2/4", "3/4", "4/4", "6/8"] {
+                let signature = TimeSignature::from_str(signature).unwrap();
+                ui.selectable_value(
+                    &mut self.metronome.time_signature,
+                    signature,
+                    signature.to_string(),
+                );
+            }
+        });
+        ui.checkbox(&mut self.metronome.accent_downbeat, "accent first beat");
+    }
+
+    /// open a .harptab file, taking the key, position, tuning, style and tempo from its header
+    fn open_tab_file(&mut self) {
+        let tab = std::fs::read_to_string(self.tab_path.trim())
+            .map_err(|e| e.to_string())
+            .and_then(|text| HarpTab::from_str(&text).map_err(|e| e.to_string()));
+        let tab = match tab {
+            Ok(tab) => tab,
+            Err(e) => {
+                self.tab_file_message = e;
+                return;
+            }
+        };
+
+        let header = &tab.header;
+        if let Some(key) = harptabber::KEYS
+            .iter()
+            .find(|k| Some(**k) == header.key.as_deref())
+        {
+            self.key = key;
+        }
+        if let Some(position) = header.position {
+            self.from_position = position;
+        }
+        if let Some(tuning) = TUNINGS
+            .iter()
+            .find(|t| Some(**t) == header.tuning.as_deref())
+        {
+            self.set_input_tuning(tuning);
+        }
+        if let Some(style) = header.style {
+            self.style = style;
+            self.set_keyboard_notes();
+        }
+        if let Some(tempo) = header.tempo {
+            self.metronome.bpm = tempo;
+            self.practice_options.start_bpm = tempo;
+            self.backing_options.bpm = tempo;
+        }
+        self.tab_header = tab.header;
+        self.input_format = InputFormat::Tab;
+        self.input_text = tab.body;
+        self.to_position =
+            harptabber::semitones_to_position(self.from_position, self.semitone_shift);
+        self.tab_file_message.clear();
+        self.transpose();
+    }
+
+    /// save the input or output tab as a .harptab file
+    fn save_tab_file(&mut self, source: TextSource) {
+        let (body, position, tuning) = match source {
+            TextSource::Input => (&self.input_text, self.from_position, self.input_tuning),
+            TextSource::Output => (&self.output_text, self.to_position, self.output_tuning),
+        };
+        let tab = HarpTab {
+            header: TabHeader {
+                key: Some(self.key.to_string()),
+                position: Some(position),
+                tuning: Some(tuning.to_string()),
+                style: Some(self.style),
+                tempo: Some(self.metronome.bpm),
+                ..self.tab_header.clone()
+            },
+            body: body.clone(),
+        };
+        self.tab_file_message = match std::fs::write(self.tab_path.trim(), tab.to_string()) {
+            Ok(()) => format!("saved {}", self.tab_path.trim()),
+            Err(e) => e.to_string(),
+        };
+    }
+
+    fn tab_file_panel(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.add(TextEdit::singleline(&mut self.tab_path).hint_text("song.harptab"));
+            if ui.button("open").clicked() {
+                self.open_tab_file();
+            }
+            if ui.button("save").clicked() {
+                self.save_tab_file(TextSource::Input);
+            }
+            if ui.button("save output").clicked() {
+                self.save_tab_file(TextSource::Output);
+            }
+        });
+        for (label, value) in [
+            ("title", &mut self.tab_header.title),
+            ("artist", &mut self.tab_header.artist),
+        ] {
+            let mut text = value.clone().unwrap_or_default();
+            ui.horizontal(|ui| {
+                ui.label(label);
+                if ui.text_edit_singleline(&mut text).changed() {
+                    *value = (!text.is_empty()).then_some(text);
+                }
+            });
+        }
+        ui.label("the key, positions, tunings, style and metronome tempo are saved along with the tab. lines like %transpose +12, %octave -1, %position 3 or %style harpsurgery apply until the next %section");
+        if !self.tab_file_message.is_empty() {
+            ui.label(&self.tab_file_message);
+        }
+    }
+
+    /// intonation of a harp in the selected key, with reed offsets for `tuning`
+    fn intonation(&mut self, tuning: &str) -> Intonation {
+        let reed_offsets = match ReedOffsets::parse(&self.reed_offsets_text) {
+            Ok(offsets) => {
+                self.reed_offsets_error.clear();
+                offsets
+            }
+            Err(e) => {
+                self.reed_offsets_error = e.to_string();
+                ReedOffsets::default()
+            }
+        };
+        Intonation::new(
+            self.temperament,
+            self.reference_pitch,
+            self.key,
+            tuning,
+            &reed_offsets,
+        )
+    }
+
+    fn apply_intonation(&mut self, tuning: &str) {
+        let intonation = self.intonation(tuning);
+        self.player.output_mut().set_intonation(intonation);
+    }
+
+    /// compare a recording to the input tab
+    fn check_recording(&mut self) {
+        match harptabber::read_wav(self.check_path.trim()) {
+            Ok((samples, sample_rate)) => {
+                let transcription = harptabber::transcribe(
+                    &samples,
+                    sample_rate,
+                    self.input_tuning,
+                    self.key,
+                    self.style,
+                );
+                self.check = Some(harptabber::check_performance(
+                    &transcription,
+                    &self.input_text,
+                    self.input_tuning,
+                    self.style,
+                    self.key,
+                ));
+                self.check_error.clear();
+            }
+            Err(e) => {
+                self.check = None;
+                self.check_error = e.to_string();
+            }
+        }
+    }
+
+    fn check_panel(&mut self, ui: &mut egui::Ui) {
+        ui.label(format!(
+            "mono wav of the input tab, played on a {} harp",
+            self.key
+        ));
+        ui.horizontal(|ui| {
+            ui.add(TextEdit::singleline(&mut self.check_path).hint_text("recording.wav"));
+            if ui.button("check").clicked() {
+                self.check_recording();
+            }
+        });
+        if !self.check_error.is_empty() {
+            ui.label(&self.check_error);
+        }
+
+        let Some(check) = &self.check else {
+            return;
+        };
+        let warn = ui.visuals().warn_fg_color;
+        let error = ui.visuals().error_fg_color;
+        egui::ScrollArea::vertical()
+            .max_height(200.0)
+            .show(ui, |ui| {
+                egui::Grid::new("check_results")
+                    .striped(true)
+                    .show(ui, |ui| {
+                        ui.label("hole");
+                        ui.label("time");
+                        ui.label("result");
+                        ui.end_row();
+                        for note in check.notes.iter() {
+                            match note {
+                                CheckedNote::Played { hole, start, cents } => {
+                                    ui.label(hole);
+                                    ui.label(format!("{:.2}s", start));
+                                    let text = RichText::new(format!("{:+.0} cents", cents));
+                                    ui.label(if cents.abs() > 20.0 {
+                                        text.color(warn)
+                                    } else {
+                                        text
+                                    });
+                                }
+                                CheckedNote::Missed { hole } => {
+                                    ui.label(hole);
+                                    ui.label("");
+                                    ui.label(RichText::new("missed").color(error));
+                                }
+                                CheckedNote::Extra { hole, start } => {
+                                    ui.label(hole);
+                                    ui.label(format!("{:.2}s", start));
+                                    ui.label(RichText::new("extra").color(error));
+                                }
+                            }
+                            ui.end_row();
+                        }
+                    });
+            });
+        if let Some(deviation) = check.average_deviation() {
+            ui.label(format!("average deviation: {:.0} cents", deviation));
+        }
+        ui.label(format!(
+            "missed notes: {}, extra notes: {}",
+            check.missed(),
+            check.extra()
+        ));
+    }
+
+    fn start_quiz(&mut self, time: f64) {
+        let options = QuizOptions {
+            question: self.quiz_question,
+            answer: self.quiz_answer,
+            tuning: self.input_tuning.to_string(),
+            key: self.key.to_string(),
+            scale: self.quiz_scale.map(|s| s.to_string()),
+            position: self.from_position,
+        };
+        self.quizzes_started += 1;
+        let seed = time.to_bits() ^ self.quizzes_started;
+        self.quiz = Some(QuizSession::new(options, seed));
+        self.next_quiz_question();
+    }
+
+    fn next_quiz_question(&mut self) {
+        self.quiz_guess.clear();
+        self.quiz_feedback.clear();
+        let has_question = self
+            .quiz
+            .as_mut()
+            .is_some_and(|quiz| quiz.next_question().is_some());
+        if has_question {
+            self.play_quiz_question();
         } else {
-            self.playable_without_bends.as_ref()
+            self.quiz_feedback = String::from("no notes to ask for");
+        }
+    }
+
+    fn play_quiz_question(&mut self) {
+        let Some(question) = self.quiz.as_ref().and_then(|quiz| quiz.current()) else {
+            return;
         };
+        let notes = question
+            .indices
+            .iter()
+            .map(|&index| PlaybackNote {
+                index,
+                chord: Vec::new(),
+                reeds: Vec::new(),
+                span: 0..0,
+            })
+            .collect();
+        let tuning = self.input_tuning;
+        self.apply_intonation(tuning);
+        self.player.set_notes(notes);
+        self.player.set_looping(false);
+        self.player.set_count_in(0);
+        self.player.set_metronome(None);
+        self.player
+            .set_note_duration(harptabber::NOTE_DURATION * 2.0);
+        self.practice = None;
+        self.player.play();
+    }
 
+    fn quiz_panel(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("play");
+            ui.selectable_value(&mut self.quiz_question, QuestionKind::Hole, "hole");
+            ui.selectable_value(&mut self.quiz_question, QuestionKind::Interval, "interval");
+        });
         ui.horizontal(|ui| {
-            ui.selectable_value(&mut self.allow_bends, true, "without overblows");
-            ui.selectable_value(&mut self.allow_bends, false, "without bends");
+            ui.label("name the");
+            ui.selectable_value(&mut self.quiz_answer, AnswerKind::Hole, "hole");
+            ui.selectable_value(&mut self.quiz_answer, AnswerKind::ScaleDegree, "degree");
+            ui.selectable_value(&mut self.quiz_answer, AnswerKind::Note, "note");
         });
+        egui::ComboBox::from_label("quiz scale")
+            .selected_text(self.quiz_scale.unwrap_or("any hole"))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut self.quiz_scale, None, "any hole");
+                for scale in self.scales.keys() {
+                    ui.selectable_value(&mut self.quiz_scale, Some(scale), scale);
+                }
+            });
+        ui.label(format!(
+            "{} harp, {} position",
+            self.key,
+            harptabber::to_ordinal(self.from_position)
+        ));
+        if ui.button("start quiz").clicked() {
+            let time = ui.input(|i| i.time);
+            self.start_quiz(time);
+        }
+
+        let Some(question) = self.quiz.as_ref().and_then(|quiz| quiz.current()).cloned() else {
+            return;
+        };
+        if let Some(given) = &question.given {
+            ui.label(format!("starting on {}, name the second note", given));
+        }
+        ui.horizontal(|ui| {
+            let response = ui.add(
+                TextEdit::singleline(&mut self.quiz_guess)
+                    .hint_text("answer")
+                    .desired_width(80.0),
+            );
+            let submitted = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            if (ui.button("answer").clicked() || submitted) && self.quiz_feedback.is_empty() {
+                let quiz = self.quiz.as_mut().unwrap();
+                self.quiz_feedback = if quiz.answer(&self.quiz_guess) {
+                    String::from("correct")
+                } else {
+                    format!("wrong, it was {}", question.answer)
+                };
+            }
+            if ui.button("replay").clicked() {
+                self.play_quiz_question();
+            }
+            if ui.button("next").clicked() {
+                self.next_quiz_question();
+            }
+        });
+        if !self.quiz_feedback.is_empty() {
+            ui.label(&self.quiz_feedback);
+        }
+
+        let quiz = self.quiz.as_ref().unwrap();
+        let score = quiz.score();
+        ui.label(format!("score: {}/{}", score.correct, score.total));
+        if !quiz.per_hole().is_empty() {
+            egui::Grid::new("quiz_accuracy")
+                .striped(true)
+                .show(ui, |ui| {
+                    for (hole, score) in quiz.per_hole() {
+                        ui.label(hole);
+                        ui.label(format!(
+                            "{}/{} ({:.0}%)",
+                            score.correct,
+                            score.total,
+                            score.accuracy() * 100.0
+                        ));
+                        ui.end_row();
+                    }
+                });
+        }
+    }
+
+    fn intonation_panel(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            let temperaments = [
+                (Temperament::Equal, "equal"),
+                (Temperament::Just, "just"),
+                (Temperament::SevenLimit, "7-limit"),
+                (Temperament::Compromise, "compromise"),
+            ];
+            for (temperament, name) in temperaments {
+                ui.selectable_value(&mut self.temperament, temperament, name);
+            }
+        });
+        ui.add(
+            Slider::new(&mut self.reference_pitch, 415.0..=466.0)
+                .text("A4 (Hz)")
+                .fixed_decimals(1),
+        );
+        ui.label("reed offsets in cents");
+        ui.add(
+            TextEdit::multiline(&mut self.reed_offsets_text)
+                .hint_text("-2 -4.5\n[paddy]\n3 +2")
+                .desired_rows(3),
+        );
+        if !self.reed_offsets_error.is_empty() {
+            ui.label(&self.reed_offsets_error);
+        }
+    }
+
+    /// remember the selected part of a text, as a byte range
+    fn store_selection(
+        &mut self,
+        source: TextSource,
+        cursor_range: Option<egui::text::CCursorRange>,
+    ) {
+        let Some(cursor_range) = cursor_range.filter(|range| !range.is_empty()) else {
+            return;
+        };
+        let text = match source {
+            TextSource::Input => &self.input_text,
+            TextSource::Output => &self.output_text,
+        };
+        let chars = cursor_range.as_sorted_char_range();
+        let to_byte = |char_index: usize| {
+            text.char_indices()
+                .nth(char_index)
+                .map_or(text.len(), |(i, _)| i)
+        };
+        self.selection = Some((source, to_byte(chars.start)..to_byte(chars.end)));
+    }
+
+    fn practice_panel(&mut self, ui: &mut egui::Ui) {
+        ui.label("select notes in the input or output tab to loop them");
+        let options = &mut self.practice_options;
+        ui.add(Slider::new(&mut options.start_bpm, 20.0..=300.0).text("starting tempo"));
+        ui.add(Slider::new(&mut options.target_bpm, 20.0..=300.0).text("target tempo"));
+        ui.add(Slider::new(&mut options.bpm_step, 1.0..=40.0).text("tempo step"));
+        ui.add(Slider::new(&mut options.loops_per_step, 1..=10).text("clean loops per step"));
+        ui.add(Slider::new(&mut options.count_in, 0..=8).text("count-in clicks"));
+
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(self.selection.is_some(), Button::new("loop selection"))
+                .clicked()
+            {
+                self.start_practice();
+            }
+            if self.practice.is_some() {
+                if ui.button("mistake").clicked() {
+                    self.loop_had_mistake = true;
+                }
+                if ui.button("stop").clicked() {
+                    self.player.stop();
+                    self.practice = None;
+                }
+            }
+        });
+
+        if let Some(session) = &self.practice {
+            let mistake = if self.loop_had_mistake {
+                ", mistake in this loop"
+            } else {
+                ""
+            };
+            ui.label(format!(
+                "{:.0} bpm, {}/{} clean loops{}",
+                session.bpm(),
+                session.clean_loops(),
+                session.options().loops_per_step,
+                mistake
+            ));
+        }
+    }
+
+    fn playback_controls(&mut self, ui: &mut egui::Ui, source: TextSource) {
+        let is_active = self.playback_source == source;
+        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+            if ui.checkbox(&mut self.loop_playback, "loop").changed() && self.practice.is_none() {
+                self.player.set_looping(self.loop_playback);
+            }
+            if ui.button("stop").clicked() && is_active {
+                self.player.stop();
+                self.practice = None;
+            }
+            if is_active && self.player.is_playing() {
+                if ui.button("pause").clicked() {
+                    self.player.pause();
+                }
+            } else if is_active && self.player.is_paused() {
+                if ui.button("resume").clicked() {
+                    self.player.resume();
+                }
+            } else if ui.button("play tab").clicked() {
+                self.start_playback(source);
+            }
+        });
+    }
+
+    fn seek_slider(&mut self, ui: &mut egui::Ui, source: TextSource) {
+        if self.playback_source != source || self.player.notes().is_empty() {
+            return;
+        }
+        let Some(mut note) = self.player.current_note() else {
+            return;
+        };
+        let last = self.player.notes().len() - 1;
+        if ui
+            .add(Slider::new(&mut note, 0..=last).text("playing note"))
+            .changed()
+        {
+            self.player.seek(note);
+        }
+    }
+
+    fn playable_positions_panel(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            let choices = [
+                (TechniqueChoice::WithoutOverblows, "without overblows"),
+                (TechniqueChoice::WithoutBends, "without bends"),
+                (TechniqueChoice::Custom, "custom"),
+            ];
+            for (choice, text) in choices {
+                if ui
+                    .selectable_value(&mut self.technique_choice, choice, text)
+                    .clicked()
+                {
+                    self.transpose();
+                }
+            }
+        });
+        if self.technique_choice == TechniqueChoice::Custom {
+            self.technique_panel(ui);
+        }
+        if ui
+            .checkbox(&mut self.split_octaves, "choose an octave per line")
+            .on_hover_text("keep the output playable in its position, marking octave changes with %octave lines")
+            .changed()
+        {
+            self.transpose();
+        }
+        if ui
+            .checkbox(&mut self.all_tunings, "all tunings")
+            .on_hover_text("search every tuning, easiest first")
+            .changed()
+        {
+            self.transpose();
+        }
+        if self.all_tunings {
+            self.all_tunings_list(ui);
+            return;
+        }
+
+        let pairs: &[(u32, i32)] = self.playable_positions.as_ref();
 
         ui.add_enabled(
             false,
@@ -224,6 +1255,9 @@ impl GUIApp {
                 .add(Button::new(
                     RichText::new(text).text_style(TextStyle::Monospace),
                 ))
+                .on_hover_ui(|ui| {
+                    ui.label(self.technique_report(*semitones, self.output_tuning));
+                })
                 .clicked()
             {
                 self.semitone_shift = *semitones;
@@ -234,6 +1268,57 @@ impl GUIApp {
         }
     }
 
+    /// the techniques the input needs shifted by `semitones` on a harp in `tuning`
+    fn technique_report(&self, semitones: i32, tuning: &str) -> String {
+        let (transposed, _) = harptabber::transpose_tabs(
+            self.input_text.clone(),
+            semitones,
+            false,
+            self.style,
+            self.input_tuning,
+            tuning,
+        );
+        TechniqueInventory::new(&transposed, tuning, self.style)
+            .report()
+            .trim_end()
+            .to_string()
+    }
+
+    fn all_tunings_list(&mut self, ui: &mut egui::Ui) {
+        ui.add_enabled(
+            false,
+            Button::new(
+                RichText::new("tuning, position, semitone change, difficulty")
+                    .text_style(TextStyle::Monospace),
+            ),
+        );
+        for playable in &self.playable_in_all_tunings {
+            let text = format!(
+                "{:24} {:5} {:+4} {:3}",
+                playable.tuning,
+                harptabber::to_ordinal(playable.position),
+                playable.semitones,
+                playable.difficulty
+            );
+
+            if ui
+                .add(Button::new(
+                    RichText::new(text).text_style(TextStyle::Monospace),
+                ))
+                .on_hover_ui(|ui| {
+                    ui.label(self.technique_report(playable.semitones, playable.tuning));
+                })
+                .clicked()
+            {
+                self.output_tuning = playable.tuning;
+                self.semitone_shift = playable.semitones;
+                self.to_position = playable.position;
+                self.transpose();
+                break;
+            }
+        }
+    }
+
     fn semitone_shift_slider_and_octave_buttons(&mut self, ui: &mut egui::Ui) {
         if ui
             .add(
@@ -291,43 +1376,90 @@ impl GUIApp {
             ui.heading("input");
             ui.add_space(20.0);
             self.tuning_selector(ui, true);
+            self.input_format_selector(ui);
         });
 
-        let input_field = egui::TextEdit::multiline(&mut self.input_text).desired_width(600.0);
-        let tedit_output = input_field.show(ui);
+        // spans of the tab don't match the melody it's converted from
+        let span = match self.input_format {
+            InputFormat::Tab => self.highlighted_span(TextSource::Input),
+            _ => None,
+        };
+        let mut layouter = highlight_layouter(span);
+        let margin = self.shift_margin();
+        let tedit_output = ui
+            .horizontal_top(|ui| {
+                if !margin.is_empty() {
+                    egui::Frame::NONE
+                        .inner_margin(egui::Margin::symmetric(0, 2))
+                        .show(ui, |ui| {
+                            ui.add(egui::Label::new(RichText::new(margin).weak()).extend());
+                        });
+                }
+                let text = match self.input_format {
+                    InputFormat::Tab => &mut self.input_text,
+                    _ => &mut self.melody_text,
+                };
+                egui::TextEdit::multiline(text)
+                    .desired_width(600.0)
+                    .layouter(&mut layouter)
+                    .show(ui)
+            })
+            .inner;
         if tedit_output.response.changed() {
             self.transpose();
         }
+        if self.input_format != InputFormat::Tab {
+            ui.add(
+                TextEdit::multiline(&mut self.input_text.as_str())
+                    .desired_width(600.0)
+                    .desired_rows(1)
+                    .interactive(false),
+            );
+        }
+        self.store_selection(TextSource::Input, tedit_output.cursor_range);
         ui.horizontal(|ui| {
             if ui.button("copy").clicked() {
                 ui.ctx().copy_text(self.input_text.clone());
             }
-            ui.add_space(ui.available_size_before_wrap().x - 97.0);
 
-            #[cfg(not(target_arch = "wasm32"))]
-            if ui.button("play tab").clicked() {
-                self.audio_context = AudioContext::new();
-                harptabber::play_tab_in_key(
-                    self.input_text.clone(),
-                    self.input_tuning,
-                    self.style,
-                    self.key,
-                    &self.audio_context.sink,
-                );
-                self.audio_context.sink.play();
-            }
-            #[cfg(not(target_arch = "wasm32"))]
-            if ui.button("stop").clicked() {
-                self.audio_context = AudioContext::new();
-            }
+            self.playback_controls(ui, TextSource::Input);
         });
+        self.seek_slider(ui, TextSource::Input);
 
         self.position_slider(ui, false);
 
+        ui.collapsing("tab file", |ui| {
+            self.tab_file_panel(ui);
+        });
+
         ui.collapsing("tab keyboard", |ui| {
             self.tabkeyboard(ui, tedit_output.response.id);
         });
 
+        ui.collapsing("practice loop", |ui| {
+            self.practice_panel(ui);
+        });
+
+        ui.collapsing("backing track", |ui| {
+            self.backing_track_panel(ui);
+        });
+
+        ui.collapsing("metronome", |ui| {
+            self.metronome_panel(ui);
+        });
+
+        ui.collapsing("temperament", |ui| {
+            self.intonation_panel(ui);
+        });
+
+        ui.collapsing("intonation check", |ui| {
+            self.check_panel(ui);
+        });
+
+        ui.collapsing("ear training", |ui| {
+            self.quiz_panel(ui);
+        });
+
         ui.collapsing("tab style", |ui| {
             ui.horizontal(|ui| {
                 self.tab_style_selector(ui);
@@ -348,7 +1480,12 @@ impl GUIApp {
             self.tuning_selector(ui, false);
         });
 
-        ui.add(TextEdit::multiline(&mut self.output_text).desired_width(800.0));
+        let mut layouter = highlight_layouter(self.highlighted_span(TextSource::Output));
+        let output = TextEdit::multiline(&mut self.output_text)
+            .desired_width(800.0)
+            .layouter(&mut layouter)
+            .show(ui);
+        self.store_selection(TextSource::Output, output.cursor_range);
 
         ui.horizontal(|ui| {
             if ui.button("copy").clicked() {
@@ -357,7 +1494,10 @@ impl GUIApp {
             if ui.checkbox(&mut self.keep_errors, "keep errors").changed() {
                 self.transpose();
             }
+
+            self.playback_controls(ui, TextSource::Output);
         });
+        self.seek_slider(ui, TextSource::Output);
         self.position_slider(ui, true);
         self.semitone_shift_slider_and_octave_buttons(ui);
 
@@ -372,6 +1512,10 @@ impl GUIApp {
         }
         ui.add_space(10.0);
 
+        ui.collapsing("hole range", |ui| {
+            self.hole_range_panel(ui);
+        });
+
         ui.collapsing("playable positions", |ui| {
             self.playable_positions_panel(ui);
         });
@@ -388,38 +1532,13 @@ impl GUIApp {
             .selected_text(tuning)
             .width(150.0)
             .show_ui(ui, |ui| {
-                for tuning_text in &[
-                    "richter",
-                    "paddy richter",
-                    "country",
-                    "natural minor",
-                    "harmonic minor",
-                    "wilde tuning",
-                    "wilde minor tuning",
-                    "pentaharp",
-                    "powerbender",
-                    "powerdraw",
-                    "diminished",
-                    "spiral",
-                    "lucky 13 diminished",
-                    "lucky 13 powerchromatic",
-                    "melody maker",
-                    "easy 3rd",
-                    "4 hole richter",
-                    "5 hole richter",
-                ] {
+                for tuning_text in &TUNINGS {
                     if ui
                         .selectable_value(&mut tuning, tuning_text, *tuning_text)
                         .changed()
                     {
                         if is_input {
-                            self.keyboard_layout = harptabber::get_tabkeyboard_layout(tuning);
-                            self.input_tuning = tuning;
-
-                            let (notes, duplicated) =
-                                harptabber::tuning_to_notes_in_order(self.input_tuning);
-                            self.notes_in_order = notes;
-                            self.duplicated_notes = duplicated;
+                            self.set_input_tuning(tuning);
                         } else {
                             self.output_tuning = tuning;
                         }
@@ -429,6 +1548,69 @@ impl GUIApp {
             });
     }
 
+    fn input_format_selector(&mut self, ui: &mut egui::Ui) {
+        let mut input_format = self.input_format;
+        egui::ComboBox::from_label("input as")
+            .selected_text(input_format_name(input_format))
+            .width(80.0)
+            .show_ui(ui, |ui| {
+                for option in [
+                    InputFormat::Tab,
+                    InputFormat::NoteNames,
+                    InputFormat::Degrees,
+                ] {
+                    ui.selectable_value(&mut input_format, option, input_format_name(option));
+                }
+            });
+        if input_format != self.input_format {
+            // start from the current tab, written the new way
+            self.melody_text = match input_format {
+                InputFormat::Tab => String::new(),
+                InputFormat::NoteNames => harptabber::tab_to_note_names(
+                    &self.input_text,
+                    self.input_tuning,
+                    self.style,
+                    self.key,
+                ),
+                InputFormat::Degrees => harptabber::tab_to_scale_degrees(
+                    &self.input_text,
+                    self.input_tuning,
+                    self.style,
+                    self.from_position,
+                ),
+            };
+            self.input_format = input_format;
+            self.transpose();
+        }
+    }
+
+    /// the text the input box edits, the tab or the melody it's converted from
+    fn edited_text(&mut self) -> &mut String {
+        match self.input_format {
+            InputFormat::Tab => &mut self.input_text,
+            _ => &mut self.melody_text,
+        }
+    }
+
+    fn set_input_tuning(&mut self, tuning: &'static str) {
+        self.keyboard_layout = harptabber::get_tabkeyboard_layout(tuning);
+        self.input_tuning = tuning;
+        self.set_keyboard_notes();
+    }
+
+    /// the notes of the input tuning in the current style, to look up the holes of the tab keyboard
+    fn set_keyboard_notes(&mut self) {
+        let (notes, duplicated) = harptabber::tuning_to_notes_in_order(self.input_tuning);
+        let styled = |notes: Vec<String>| {
+            notes
+                .iter()
+                .map(|note| harptabber::change_tab_style_single(note, self.style))
+                .collect()
+        };
+        self.notes_in_order = styled(notes);
+        self.duplicated_notes = styled(duplicated);
+    }
+
     fn tab_style_selector(&mut self, ui: &mut egui::Ui) {
         let tab_styles = [
             (Style::Default, "default", "-2 -2'' -3 4 -4 5 5o 6"),
@@ -444,6 +1626,7 @@ impl GUIApp {
         for (value, name, example) in tab_styles {
             if ui.selectable_value(&mut self.style, value, name).clicked() {
                 self.style_example = example;
+                self.set_keyboard_notes();
                 self.transpose();
             }
         }
@@ -453,7 +1636,7 @@ impl GUIApp {
         if let Some(mut state) = egui::TextEdit::load_state(ui.ctx(), tedit_id) {
             use egui::TextBuffer as _;
             if let Some(ccursor) = state.cursor.char_range() {
-                self.input_text.insert_text(text, ccursor.primary.index);
+                self.edited_text().insert_text(text, ccursor.primary.index);
                 let new_ccursor =
                     egui::text::CCursor::new(ccursor.primary.index + text.chars().count());
                 state
@@ -508,12 +1691,8 @@ impl GUIApp {
                         }
                     });
 
-                let mut _space = 214.0;
-                #[cfg(not(target_arch = "wasm32"))]
-                {
-                    _space -= 82.0;
-                    ui.checkbox(&mut self.should_play_note, "play notes");
-                }
+                let space = 132.0;
+                ui.checkbox(&mut self.should_play_note, "play notes");
 
                 if self.display_as == DisplayOption::Notes || self.should_play_note {
                     egui::ComboBox::from_label("key")
@@ -524,13 +1703,14 @@ impl GUIApp {
                                 "C", "Db", "D", "Eb", "E", "F", "F#", "G", "Ab", "A", "Bb", "B",
                             ] {
                                 if ui.selectable_value(&mut self.key, note, *note).changed() {
-                                    self.generate_keyboard_text();
+                                    // note names are converted for the key
+                                    self.transpose();
                                 }
                             }
                         });
-                    ui.add_space(_space);
+                    ui.add_space(space);
                 } else {
-                    ui.add_space(_space + 95.0);
+                    ui.add_space(space + 95.0);
                 }
                 if ui
                     .add(Button::new(
@@ -591,30 +1771,52 @@ impl GUIApp {
                                 .fill(egui::ecolor::Color32::TRANSPARENT),
                             );
                         } else {
+                            // the layout is in the default style, the keyboard notes in the current one
+                            let hole = harptabber::change_tab_style_single(hole, self.style);
                             let display_note = match self.display_as {
                                 DisplayOption::Notes => harptabber::tab_to_note(
-                                    hole,
+                                    &hole,
                                     self.key,
                                     &self.notes_in_order,
                                     &self.duplicated_notes,
                                 )
                                 .to_owned(),
                                 DisplayOption::Degrees => harptabber::tab_to_scale_degree(
-                                    hole,
+                                    &hole,
                                     self.from_position,
                                     &self.notes_in_order,
                                     &self.duplicated_notes,
                                 )
                                 .to_owned(),
-                                DisplayOption::Tabs => {
-                                    harptabber::change_tab_style_single(hole, self.style)
-                                }
+                                DisplayOption::Tabs => hole.clone(),
                             };
 
-                            // determine color of button, depending on scale being highlighted
-                            let color = self.get_tabkeyboard_button_color(ui, hole);
+                            // what the button types, in the notation of the input box
+                            let typed = match self.input_format {
+                                InputFormat::Tab => hole.clone(),
+                                InputFormat::NoteNames => harptabber::tab_to_note(
+                                    &hole,
+                                    self.key,
+                                    &self.notes_in_order,
+                                    &self.duplicated_notes,
+                                )
+                                .to_owned(),
+                                InputFormat::Degrees => harptabber::tab_to_scale_degree(
+                                    &hole,
+                                    self.from_position,
+                                    &self.notes_in_order,
+                                    &self.duplicated_notes,
+                                )
+                                .to_owned(),
+                            };
 
-                            let hole = harptabber::change_tab_style_single(hole, self.style);
+                            // determine color of button, depending on scale being highlighted
+                            // and the note currently being played
+                            let color = if self.playing_hole.as_ref() == Some(&hole) {
+                                ui.ctx().style().visuals.warn_fg_color
+                            } else {
+                                self.get_tabkeyboard_button_color(ui, &hole)
+                            };
 
                             let text = format!("{:width$}", &display_note, width = 5);
                             if ui
@@ -627,19 +1829,24 @@ impl GUIApp {
                                 )
                                 .clicked()
                             {
-                                self.insert_text_at_pos(ui, hole.as_str(), tedit_id);
+                                self.insert_text_at_pos(ui, typed.as_str(), tedit_id);
                                 self.insert_text_at_pos(ui, " ", tedit_id);
                                 self.transpose();
 
-                                #[cfg(not(target_arch = "wasm32"))]
                                 if self.should_play_note {
-                                    harptabber::play_tab_in_key(
-                                        hole,
+                                    self.apply_intonation(self.input_tuning);
+                                    for note in harptabber::get_playback_notes_in_key(
+                                        &hole,
                                         self.input_tuning,
                                         self.style,
                                         self.key,
-                                        &self.audio_context.sink,
-                                    );
+                                    ) {
+                                        self.player.output_mut().play_note(
+                                            note.index,
+                                            note.reeds.first().copied(),
+                                            harptabber::NOTE_DURATION,
+                                        );
+                                    }
                                 }
                             }
                         }
@@ -670,11 +1877,11 @@ impl GUIApp {
                 let mut deletion_start_point = ccursor.primary.index;
                 deletion_start_point = deletion_start_point.saturating_sub(1);
 
-                let chars: Vec<char> = self.input_text.chars().collect();
+                let chars: Vec<char> = self.edited_text().chars().collect();
                 while deletion_start_point > 0 && chars[deletion_start_point - 1] != ' ' {
                     deletion_start_point -= 1;
                 }
-                self.input_text
+                self.edited_text()
                     .delete_char_range(deletion_start_point..(ccursor.primary.index));
 
                 let new_ccursor = egui::text::CCursor::new(deletion_start_point);
@@ -684,11 +1891,12 @@ impl GUIApp {
                 state.store(ui.ctx(), tedit_id);
                 ui.ctx().memory_mut(|mem| mem.request_focus(tedit_id)); // give focus back to the [`TextEdit`].
             } else {
-                self.input_text.pop();
-                let mut last = self.input_text.chars().last();
+                let text = self.edited_text();
+                text.pop();
+                let mut last = text.chars().last();
                 while last.is_some() && last.unwrap() != ' ' {
-                    self.input_text.pop();
-                    last = self.input_text.chars().last();
+                    text.pop();
+                    last = text.chars().last();
                 }
             }
         }
@@ -738,6 +1946,7 @@ impl GUIApp {
         ui.menu_button("Scales", |ui| {
             for scale in self.scales.keys() {
                 if ui.button(scale).clicked() {
+                    self.input_format = InputFormat::Tab;
                     self.input_text = harptabber::scale_to_tab(
                         scale,
                         self.input_tuning,
@@ -788,3 +1997,58 @@ impl GUIApp {
         self.keyboard_text = text;
     }
 }
+
+/// buttons to choose which notes of a hole an end of the hole range allows, true if changed
+fn bound_note_buttons(ui: &mut egui::Ui, note: &mut BoundNote) -> bool {
+    ui.horizontal(|ui| {
+        let mut changed = ui
+            .selectable_value(note, BoundNote::AnyNote, "any note")
+            .changed();
+        changed |= ui.selectable_value(note, BoundNote::Blow, "blow").changed();
+        changed |= ui.selectable_value(note, BoundNote::Draw, "draw").changed();
+        changed
+    })
+    .inner
+}
+
+fn input_format_name(format: InputFormat) -> &'static str {
+    match format {
+        InputFormat::Tab => "Tabs",
+        InputFormat::NoteNames => "Notes",
+        InputFormat::Degrees => "Degrees",
+    }
+}
+
+/// lay out text normally, except for highlighting a range of it
+fn highlight_layouter(
+    span: Option<Range<usize>>,
+) -> impl FnMut(&egui::Ui, &dyn egui::TextBuffer, f32) -> Arc<egui::Galley> {
+    move |ui, buf, wrap_width| {
+        let text = buf.as_str();
+        let font_id = TextStyle::Body.resolve(ui.style());
+        let color = ui.visuals().widgets.inactive.text_color();
+        let format = TextFormat::simple(font_id, color);
+
+        let mut job = LayoutJob::default();
+        match span
+            .as_ref()
+            .filter(|span| text.get((*span).clone()).is_some())
+        {
+            Some(span) => {
+                job.append(&text[..span.start], 0.0, format.clone());
+                job.append(
+                    &text[span.clone()],
+                    0.0,
+                    TextFormat {
+                        background: ui.visuals().selection.bg_fill,
+                        ..format.clone()
+                    },
+                );
+                job.append(&text[span.end..], 0.0, format);
+            }
+            None => job.append(text, 0.0, format),
+        }
+        job.wrap.max_width = wrap_width;
+        ui.fonts_mut(|f| f.layout_job(job))
+    }
+}
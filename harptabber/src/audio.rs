@@ -1,10 +1,95 @@
+use crate::playback::{AudioOutput, NOTE_DURATION};
+use crate::synth::{index_to_frequency, render_with_intonation, Voice, SAMPLE_RATE};
+use crate::temperament::{Intonation, Reed};
+use rodio::buffer::SamplesBuffer;
 use rodio::source::{SineWave, Source};
-use std::time::Duration;
+use rodio::{OutputStream, OutputStreamHandle, Sink};
+use std::time::{Duration, Instant};
 
 pub fn play(index: i32, sink: &rodio::Sink) {
-    let freq = f32::powf(2.0, index as f32 / 12.0) * 440.0;
+    play_for(index, NOTE_DURATION, sink);
+}
+
+fn play_for(index: i32, seconds: f64, sink: &rodio::Sink) {
+    let freq = index_to_frequency(index);
     let source = SineWave::new(freq as u32)
-        .take_duration(Duration::from_secs_f32(0.3))
+        .take_duration(Duration::from_secs_f64(seconds))
         .amplify(0.20);
     sink.append(source);
 }
+
+/// audio output using the default rodio output device.
+/// every sound gets its own sink, so sounds can overlap
+pub struct RodioOutput {
+    _stream: OutputStream,
+    stream_handle: OutputStreamHandle,
+    sinks: Vec<Sink>,
+    start: Instant,
+    intonation: Intonation,
+}
+
+impl RodioOutput {
+    pub fn new() -> Self {
+        let (_stream, stream_handle) = OutputStream::try_default().unwrap();
+        Self {
+            _stream,
+            stream_handle,
+            sinks: Vec::new(),
+            start: Instant::now(),
+            intonation: Intonation::default(),
+        }
+    }
+
+    pub fn set_intonation(&mut self, intonation: Intonation) {
+        self.intonation = intonation;
+    }
+
+    /// block until everything that was started has finished playing
+    pub fn sleep_until_end(&self) {
+        for sink in self.sinks.iter() {
+            sink.sleep_until_end();
+        }
+    }
+
+    fn new_sink(&mut self) -> &Sink {
+        self.sinks.retain(|sink| !sink.empty());
+        self.sinks.push(Sink::try_new(&self.stream_handle).unwrap());
+        self.sinks.last().unwrap()
+    }
+}
+
+impl Default for RodioOutput {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AudioOutput for RodioOutput {
+    fn now(&self) -> f64 {
+        self.start.elapsed().as_secs_f64()
+    }
+
+    fn play_note(&mut self, index: i32, reed: Option<Reed>, duration: f64) {
+        // rendered instead of using a rodio sine wave, which only supports whole Hz
+        let voice = Voice {
+            index,
+            reed,
+            start: 0.0,
+            duration,
+            amplitude: 0.20,
+        };
+        let samples = render_with_intonation(&[voice], &self.intonation);
+        self.play_samples(&samples);
+    }
+
+    fn play_samples(&mut self, samples: &[f32]) {
+        let source = SamplesBuffer::new(1, SAMPLE_RATE, samples.to_vec());
+        self.new_sink().append(source);
+    }
+
+    fn stop(&mut self) {
+        for sink in self.sinks.drain(..) {
+            sink.stop();
+        }
+    }
+}
@@ -0,0 +1,277 @@
+use crate::metronome::Metronome;
+use crate::playback::PlaybackNote;
+use crate::synth::{melody_voices, render_with_intonation, Voice};
+use crate::temperament::Intonation;
+use crate::{key_to_index_offset, positions_to_semitones};
+use regex::Regex;
+use std::str::FromStr;
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum BackingError {
+    #[error("`{0}` is not a valid chord")]
+    InvalidChord(String),
+    #[error("chord progression is empty")]
+    EmptyProgression,
+}
+
+/// a chord relative to the song key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Chord {
+    /// semitones above the tonic
+    pub root: i32,
+    pub minor: bool,
+    pub seventh: bool,
+}
+
+impl FromStr for Chord {
+    type Err = BackingError;
+
+    /// parse a roman numeral like `IV`, `ii`, `bVII` or `V7`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        lazy_static! {
+            static ref RE: Regex =
+                Regex::new(r"^(?P<acc>[b#]?)(?P<numeral>[IV]+|[iv]+)(?P<seventh>7?)$").unwrap();
+        }
+        let numerals = ["I", "II", "III", "IV", "V", "VI", "VII"];
+        let roots = [0, 2, 4, 5, 7, 9, 11];
+
+        let invalid = || BackingError::InvalidChord(s.to_string());
+        let caps = RE.captures(s).ok_or_else(invalid)?;
+        let numeral = &caps["numeral"];
+        let degree = numerals
+            .iter()
+            .position(|n| *n == numeral.to_uppercase())
+            .ok_or_else(invalid)?;
+        let accidental = match &caps["acc"] {
+            "b" => -1,
+            "#" => 1,
+            _ => 0,
+        };
+
+        Ok(Chord {
+            root: roots[degree] + accidental,
+            minor: numeral.chars().all(|c| c.is_lowercase()),
+            seventh: !caps["seventh"].is_empty(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Progression {
+    /// the tonic, held throughout
+    Drone,
+    TwelveBar,
+    /// 12-bar blues going to the IV chord in the second bar
+    QuickChange,
+    Custom(Vec<Chord>),
+}
+
+impl Progression {
+    /// the chords of the progression, one per bar
+    pub fn chords(&self) -> Vec<Chord> {
+        let numerals = match self {
+            Progression::Drone => return vec![Chord::from_str("I").unwrap()],
+            Progression::TwelveBar => "I7 I7 I7 I7 IV7 IV7 I7 I7 V7 IV7 I7 V7",
+            Progression::QuickChange => "I7 IV7 I7 I7 IV7 IV7 I7 I7 V7 IV7 I7 V7",
+            Progression::Custom(chords) => return chords.clone(),
+        };
+        numerals
+            .split_whitespace()
+            .map(|n| Chord::from_str(n).unwrap())
+            .collect()
+    }
+}
+
+impl FromStr for Progression {
+    type Err = BackingError;
+
+    /// parse `drone`, `12-bar`, `quick-change` or space separated roman numerals
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "drone" => Ok(Progression::Drone),
+            "12-bar" => Ok(Progression::TwelveBar),
+            "quick-change" => Ok(Progression::QuickChange),
+            numerals => {
+                let chords = numerals
+                    .split(|c: char| c.is_whitespace() || c == '|' || c == '-')
+                    .filter(|n| !n.is_empty())
+                    .map(Chord::from_str)
+                    .collect::<Result<Vec<Chord>, BackingError>>()?;
+                if chords.is_empty() {
+                    return Err(BackingError::EmptyProgression);
+                }
+                Ok(Progression::Custom(chords))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BackingOptions {
+    pub progression: Progression,
+    /// beats per minute, with one tab note per beat
+    pub bpm: f64,
+    /// at least 1, less is played as 1
+    pub beats_per_chord: u32,
+}
+
+impl Default for BackingOptions {
+    fn default() -> Self {
+        Self {
+            progression: Progression::TwelveBar,
+            bpm: 90.0,
+            beats_per_chord: 4,
+        }
+    }
+}
+
+/// name of the key a song is in, when played on a harp in `harp_key` in a certain position
+pub fn song_key(harp_key: &str, position: u32) -> &'static str {
+    let chromatic_notes = [
+        "C", "Db", "D", "Eb", "E", "F", "F#", "G", "Ab", "A", "Bb", "B",
+    ];
+    let harp = key_to_index_offset(harp_key);
+    let shift = positions_to_semitones(1, position as i32, 0);
+    chromatic_notes[(harp + shift).rem_euclid(12) as usize]
+}
+
+/// note index of the song's tonic, in the octave below middle C (with 0 = A440)
+fn tonic_index(harp_key: &str, position: u32) -> i32 {
+    let harp = key_to_index_offset(harp_key);
+    let shift = positions_to_semitones(1, position as i32, 0);
+    -21 + (harp + shift).rem_euclid(12)
+}
+
+/// voices for a backing track lasting at least `min_beats`, always playing the whole progression
+pub fn backing_voices(
+    options: &BackingOptions,
+    harp_key: &str,
+    position: u32,
+    min_beats: usize,
+) -> Vec<Voice> {
+    let chords = options.progression.chords();
+    let beat = 60.0 / options.bpm;
+    let beats_per_chord = options.beats_per_chord.max(1);
+    let chord_duration = beat * beats_per_chord as f64;
+    let progression_beats = chords.len() * beats_per_chord as usize;
+    let repeats = min_beats.div_ceil(progression_beats).max(1);
+    let tonic = tonic_index(harp_key, position);
+
+    let mut voices = Vec::new();
+    for (i, chord) in chords
+        .iter()
+        .cycle()
+        .take(chords.len() * repeats)
+        .enumerate()
+    {
+        let start = i as f64 * chord_duration;
+        let root = tonic + chord.root;
+
+        let mut intervals = if options.progression == Progression::Drone {
+            vec![-12, 0, 7]
+        } else {
+            vec![-12, 0, if chord.minor { 3 } else { 4 }, 7]
+        };
+        if chord.seventh {
+            intervals.push(10);
+        }
+        for interval in intervals {
+            voices.push(Voice {
+                index: root + interval,
+                reed: None,
+                start,
+                duration: chord_duration,
+                amplitude: 0.06,
+            });
+        }
+    }
+    voices
+}
+
+/// render a backing track, mixed with a melody played at the same tempo and optional metronome clicks
+pub fn render_backing_track(
+    options: &BackingOptions,
+    harp_key: &str,
+    position: u32,
+    melody: &[PlaybackNote],
+    metronome: Option<&Metronome>,
+    intonation: &Intonation,
+) -> Vec<f32> {
+    let mut voices = backing_voices(options, harp_key, position, melody.len());
+    let beats = voices
+        .iter()
+        .map(|v| v.start + v.duration)
+        .fold(0.0, f64::max)
+        * options.bpm
+        / 60.0;
+    voices.extend(melody_voices(melody, 60.0 / options.bpm));
+    if let Some(metronome) = metronome {
+        voices.extend(metronome.click_voices(beats.round() as usize));
+    }
+    render_with_intonation(&voices, intonation)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_progression() {
+        let res = Progression::from_str("I | bVII7 - ii").unwrap();
+        let expected = Progression::Custom(vec![
+            Chord {
+                root: 0,
+                minor: false,
+                seventh: false,
+            },
+            Chord {
+                root: 10,
+                minor: false,
+                seventh: true,
+            },
+            Chord {
+                root: 2,
+                minor: true,
+                seventh: false,
+            },
+        ]);
+        assert_eq!(res, expected);
+
+        assert_eq!(
+            Progression::from_str("I Vi"),
+            Err(BackingError::InvalidChord("Vi".to_string()))
+        );
+        assert_eq!(Progression::from_str("12-bar").unwrap().chords().len(), 12);
+    }
+
+    #[test]
+    fn test_song_key() {
+        assert_eq!(song_key("C", 1), "C");
+        assert_eq!(song_key("C", 2), "G");
+        assert_eq!(song_key("A", 2), "E");
+        assert_eq!(song_key("F#", 3), "Ab");
+        assert_eq!(tonic_index("C", 2), -14);
+    }
+
+    #[test]
+    fn test_backing_voices() {
+        let options = BackingOptions {
+            progression: Progression::Drone,
+            bpm: 60.0,
+            beats_per_chord: 4,
+        };
+        let voices = backing_voices(&options, "C", 2, 6);
+        assert_eq!(voices.len(), 6);
+        assert_eq!(voices[0].index, -26);
+        assert_eq!(voices[3].start, 4.0);
+
+        let options = BackingOptions {
+            beats_per_chord: 0,
+            ..options
+        };
+        let voices = backing_voices(&options, "C", 2, 6);
+        assert_eq!(voices.len(), 18);
+        assert_eq!(voices[3].start, 1.0);
+    }
+}
@@ -0,0 +1,434 @@
+use crate::harptab::{transpose_body, HarpTab, TabHeader};
+use crate::{
+    count_unplayable_notes, exit_code, positions_to_semitones, semitones_to_position, Style,
+};
+use serde::ser::SerializeMap;
+use serde::{Serialize, Serializer};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use thiserror::Error;
+
+/// extensions of the files picked up from directories
+pub const TAB_EXTENSIONS: [&str; 3] = ["txt", "tab", "harptab"];
+
+#[derive(Error, Debug)]
+pub enum BatchError {
+    #[error("invalid pattern `{0}`: {1}")]
+    InvalidPattern(String, String),
+    #[error("no tab files found for `{0}`")]
+    NotFound(String),
+    #[error("could not read `{0}`: {1}")]
+    Read(PathBuf, std::io::Error),
+}
+
+/// a tab file to transpose
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatchFile {
+    pub path: PathBuf,
+    /// where the output goes, relative to the output directory
+    pub relative: PathBuf,
+}
+
+/// how every file of a batch is transposed. `None` fields are taken from the headers of
+/// `.harptab` files, like for single files
+#[derive(Debug, Clone)]
+pub struct BatchOptions<'a> {
+    pub semitones: i32,
+    pub from_position: Option<i32>,
+    pub to_position: Option<i32>,
+    pub octave_shift: i32,
+    pub keep_errors: bool,
+    pub style: Option<Style>,
+    pub input_tuning: Option<&'a str>,
+    pub output_tuning: &'a str,
+    pub key: Option<&'a str>,
+}
+
+/// the transposed text of a file, along with its problems
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TransposedFile {
+    pub text: String,
+    /// tokens that aren't notes on the input harp
+    pub invalid_notes: Vec<String>,
+    /// notes that aren't on the output harp, marked with `X`
+    pub unplayable_notes: usize,
+}
+
+/// what happened to one file of a batch
+#[derive(Debug)]
+pub struct BatchResult {
+    pub file: BatchFile,
+    pub result: Result<TransposedFile, String>,
+}
+
+impl Serialize for BatchResult {
+    /// the paths of the file, then either the transposed file or an `error`
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("path", &self.file.path)?;
+        map.serialize_entry("relative", &self.file.relative)?;
+        match &self.result {
+            Ok(transposed) => {
+                map.serialize_entry("text", &transposed.text)?;
+                map.serialize_entry("invalid_notes", &transposed.invalid_notes)?;
+                map.serialize_entry("unplayable_notes", &transposed.unplayable_notes)?;
+            }
+            Err(e) => map.serialize_entry("error", e)?,
+        }
+        map.end()
+    }
+}
+
+/// per-file problems of a batch, printed at the end
+#[derive(Debug, Default, Serialize)]
+pub struct BatchSummary {
+    pub results: Vec<BatchResult>,
+}
+
+fn has_tab_extension(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| TAB_EXTENSIONS.contains(&e))
+}
+
+/// tab files in a directory and its subdirectories, sorted
+fn walk_dir(dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), BatchError> {
+    let entries = fs::read_dir(dir).map_err(|e| BatchError::Read(dir.to_path_buf(), e))?;
+    let mut paths: Vec<PathBuf> = entries.filter_map(|e| e.ok()).map(|e| e.path()).collect();
+    paths.sort();
+    for path in paths {
+        if path.is_dir() {
+            walk_dir(&path, files)?;
+        } else if has_tab_extension(&path) {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// the directory a glob pattern starts in, up to its first wildcard
+fn pattern_base(pattern: &str) -> PathBuf {
+    let literal = pattern.split(['*', '?', '[']).next().unwrap_or_default();
+    match literal.rfind('/') {
+        Some(i) => PathBuf::from(&literal[..=i]),
+        None => PathBuf::new(),
+    }
+}
+
+/// the files given as paths, directories (searched recursively) or glob patterns.
+/// files keep their path below the directory or the start of the pattern they were found in
+pub fn collect_tab_files(inputs: &[&str]) -> Result<Vec<BatchFile>, BatchError> {
+    let mut res = Vec::new();
+    for &input in inputs {
+        let path = Path::new(input);
+        let (base, matches) = if path.is_file() {
+            let base = path.parent().unwrap_or(Path::new("")).to_path_buf();
+            (base, vec![path.to_path_buf()])
+        } else if path.is_dir() {
+            (path.to_path_buf(), vec![path.to_path_buf()])
+        } else {
+            let matches = glob::glob(input)
+                .map_err(|e| BatchError::InvalidPattern(input.to_string(), e.to_string()))?
+                .filter_map(|m| m.ok())
+                .collect();
+            (pattern_base(input), matches)
+        };
+
+        let mut found = Vec::new();
+        for path in matches {
+            if path.is_dir() {
+                walk_dir(&path, &mut found)?;
+            } else {
+                found.push(path);
+            }
+        }
+        if found.is_empty() {
+            return Err(BatchError::NotFound(input.to_string()));
+        }
+        res.extend(found.into_iter().map(|path| BatchFile {
+            relative: path.strip_prefix(&base).unwrap_or(&path).to_path_buf(),
+            path,
+        }));
+    }
+    Ok(res)
+}
+
+/// transpose the text of a file, `.harptab` files keep their header with the new position and tuning
+pub fn transpose_file(
+    text: &str,
+    is_harptab: bool,
+    options: &BatchOptions,
+) -> Result<TransposedFile, String> {
+    let document = if is_harptab {
+        HarpTab::from_str(text).map_err(|e| format!("invalid harptab file: {}", e))?
+    } else {
+        HarpTab {
+            header: TabHeader::default(),
+            body: text.to_string(),
+        }
+    };
+    let header = &document.header;
+    let from_position = options
+        .from_position
+        .unwrap_or(header.position.map_or(1, |p| p as i32));
+    let style = options.style.or(header.style).unwrap_or(Style::Default);
+    let input_tuning = options
+        .input_tuning
+        .unwrap_or(header.tuning.as_deref().unwrap_or("richter"));
+
+    let semitones = match options.to_position {
+        Some(to_position) => {
+            positions_to_semitones(from_position, to_position, options.octave_shift)
+        }
+        None if options.octave_shift != 0 => {
+            positions_to_semitones(from_position, 1, options.octave_shift)
+        }
+        None => options.semitones,
+    };
+
+    let (body, invalid_notes) = transpose_body(
+        &document.body,
+        semitones,
+        from_position as u32,
+        options.keep_errors,
+        style,
+        input_tuning,
+        options.output_tuning,
+    );
+    let unplayable_notes = count_unplayable_notes(&body);
+    let text = if is_harptab {
+        let output = HarpTab {
+            header: TabHeader {
+                key: options.key.map(str::to_string).or(header.key.clone()),
+                position: Some(semitones_to_position(from_position as u32, semitones)),
+                tuning: Some(options.output_tuning.to_string()),
+                style: Some(style),
+                ..header.clone()
+            },
+            body,
+        };
+        output.to_string()
+    } else {
+        body
+    };
+    Ok(TransposedFile {
+        text,
+        invalid_notes,
+        unplayable_notes,
+    })
+}
+
+/// where the files of a batch are written
+#[derive(Debug, Clone, Copy)]
+pub enum BatchOutput<'a> {
+    /// below a directory, in the same tree as the inputs
+    Dir(&'a Path),
+    /// over the input files, keeping text that isn't a note
+    InPlace,
+    /// nowhere, only kept in the results to be printed
+    Collect,
+}
+
+/// transpose every file, writing the results to `output`.
+/// a file that would be written over the output of another one fails
+pub fn transpose_batch(
+    files: Vec<BatchFile>,
+    options: &BatchOptions,
+    output: BatchOutput,
+) -> BatchSummary {
+    let options = &BatchOptions {
+        keep_errors: options.keep_errors || matches!(output, BatchOutput::InPlace),
+        ..options.clone()
+    };
+    let mut summary = BatchSummary::default();
+    let mut written: Vec<(PathBuf, PathBuf)> = Vec::new();
+    for file in files {
+        let result = fs::read_to_string(&file.path)
+            .map_err(|e| format!("could not read file: {}", e))
+            .and_then(|text| {
+                let is_harptab = file.path.extension().is_some_and(|e| e == "harptab");
+                transpose_file(&text, is_harptab, options)
+            })
+            .and_then(|transposed| {
+                let path = match output {
+                    BatchOutput::Dir(dir) => dir.join(&file.relative),
+                    BatchOutput::InPlace => file.path.clone(),
+                    BatchOutput::Collect => return Ok(transposed),
+                };
+                if let Some((_, other)) = written.iter().find(|(p, _)| *p == path) {
+                    return Err(format!(
+                        "`{}` was already written for `{}`",
+                        path.display(),
+                        other.display()
+                    ));
+                }
+                written.push((path.clone(), file.path.clone()));
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)
+                        .map_err(|e| format!("could not create directory: {}", e))?;
+                }
+                fs::write(&path, &transposed.text)
+                    .map_err(|e| format!("could not write file: {}", e))?;
+                Ok(transposed)
+            });
+        summary.results.push(BatchResult { file, result });
+    }
+    summary
+}
+
+impl BatchSummary {
+    /// -1 if any file failed, otherwise the exit code for the invalid and unplayable notes of all files
+    pub fn exit_code(&self) -> i32 {
+        let transposed: Vec<&TransposedFile> = self
+            .results
+            .iter()
+            .filter_map(|r| r.result.as_ref().ok())
+            .collect();
+        if transposed.len() < self.results.len() {
+            return -1;
+        }
+        exit_code(
+            transposed.iter().any(|t| !t.invalid_notes.is_empty()),
+            transposed.iter().any(|t| t.unplayable_notes > 0),
+        )
+    }
+}
+
+impl fmt::Display for BatchSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let failed = self.results.iter().filter(|r| r.result.is_err()).count();
+        writeln!(
+            f,
+            "transposed {} of {} files",
+            self.results.len() - failed,
+            self.results.len()
+        )?;
+        for result in self.results.iter() {
+            let path = result.file.path.display();
+            match &result.result {
+                Err(e) => writeln!(f, "{}: {}", path, e)?,
+                Ok(transposed) => {
+                    if !transposed.invalid_notes.is_empty() {
+                        writeln!(
+                            f,
+                            "{}: invalid notes: {}",
+                            path,
+                            transposed.invalid_notes.join(" ")
+                        )?;
+                    }
+                    if transposed.unplayable_notes > 0 {
+                        writeln!(
+                            f,
+                            "{}: {} notes not on the output harp",
+                            path, transposed.unplayable_notes
+                        )?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options() -> BatchOptions<'static> {
+        BatchOptions {
+            semitones: 0,
+            from_position: None,
+            to_position: Some(1),
+            octave_shift: 0,
+            keep_errors: false,
+            style: None,
+            input_tuning: None,
+            output_tuning: "richter",
+            key: None,
+        }
+    }
+
+    #[test]
+    fn test_transpose_file() {
+        // the header says 2nd position
+        let text = "title: test\nkey: A\nposition: 2\n---\n-2 -3 4 asdf\n";
+        let res = transpose_file(text, true, &options()).unwrap();
+        assert_eq!(
+            res.text,
+            "title: test\nkey: A\nposition: 1\ntuning: richter\nstyle: default\n---\n4 5 -5 \n"
+        );
+        assert_eq!(res.invalid_notes, vec!["asdf"]);
+        assert_eq!(res.unplayable_notes, 0);
+
+        let res = transpose_file("-2 -3 4 asdf\n", false, &options()).unwrap();
+        assert_eq!(res.text, "-2 -3 4 \n");
+        assert!(transpose_file("key: H\n---\n4", true, &options()).is_err());
+    }
+
+    #[test]
+    fn test_collect_tab_files() {
+        let dir = std::env::temp_dir().join(format!("harptabber-batch-{}", std::process::id()));
+        fs::create_dir_all(dir.join("blues")).unwrap();
+        fs::write(dir.join("a.txt"), "4 5 6").unwrap();
+        fs::write(dir.join("blues/b.harptab"), "---\n-2 -3").unwrap();
+        fs::write(dir.join("notes.md"), "not a tab").unwrap();
+
+        let files = collect_tab_files(&[dir.to_str().unwrap()]).unwrap();
+        let relative: Vec<&Path> = files.iter().map(|f| f.relative.as_path()).collect();
+        assert_eq!(
+            relative,
+            vec![Path::new("a.txt"), Path::new("blues/b.harptab")]
+        );
+
+        let pattern = format!("{}/**/*.harptab", dir.display());
+        let files = collect_tab_files(&[&pattern]).unwrap();
+        assert_eq!(files[0].relative, Path::new("blues/b.harptab"));
+
+        let output = dir.join("out");
+        let summary = transpose_batch(files, &options(), BatchOutput::Dir(&output));
+        assert_eq!(summary.exit_code(), 0);
+        assert!(output.join("blues/b.harptab").is_file());
+
+        // files with the same name in different directories would overwrite each other
+        let blues_a = dir.join("blues/a.txt");
+        fs::write(&blues_a, "4 -4").unwrap();
+        let a = dir.join("a.txt");
+        let files = collect_tab_files(&[a.to_str().unwrap(), blues_a.to_str().unwrap()]).unwrap();
+        let summary = transpose_batch(files, &options(), BatchOutput::Dir(&output));
+        assert!(summary.results[1].result.is_err());
+        assert_eq!(summary.exit_code(), -1);
+        assert_eq!(
+            fs::read_to_string(output.join("a.txt")).unwrap(),
+            "4 5 6 \n"
+        );
+
+        let files = collect_tab_files(&[a.to_str().unwrap()]).unwrap();
+        let summary = transpose_batch(files, &options(), BatchOutput::Collect);
+        let json = serde_json::to_value(&summary).unwrap();
+        assert_eq!(json["results"][0]["relative"], "a.txt");
+        assert_eq!(json["results"][0]["text"], "4 5 6 \n");
+
+        // lyrics and chords stay in files written in place
+        fs::write(&a, "G\n4 5 6 la la\n").unwrap();
+        let files = collect_tab_files(&[a.to_str().unwrap()]).unwrap();
+        let options = BatchOptions {
+            semitones: -12,
+            to_position: None,
+            ..options()
+        };
+        let summary = transpose_batch(files, &options, BatchOutput::InPlace);
+        assert_eq!(
+            summary.results[0]
+                .result
+                .as_ref()
+                .unwrap()
+                .invalid_notes
+                .len(),
+            3
+        );
+        assert_eq!(fs::read_to_string(&a).unwrap(), "G \n1 2 -2 la la \n");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
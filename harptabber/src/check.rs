@@ -0,0 +1,210 @@
+use crate::transcribe::Transcription;
+use crate::{get_playback_notes_in_key, Style};
+use serde::Serialize;
+
+/// detected notes further than this from the expected note (in semitones) count as wrong notes
+const MAX_DEVIATION: f32 = 1.0;
+
+/// how a note of the reference tab was played
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum CheckedNote {
+    Played {
+        hole: String,
+        /// start time in the recording, in seconds
+        start: f64,
+        /// deviation from the expected pitch
+        cents: f32,
+    },
+    Missed {
+        hole: String,
+    },
+    /// a note in the recording that isn't in the tab
+    Extra {
+        hole: String,
+        start: f64,
+    },
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct PerformanceCheck {
+    pub notes: Vec<CheckedNote>,
+}
+
+impl PerformanceCheck {
+    /// average absolute deviation of all played notes, in cents
+    pub fn average_deviation(&self) -> Option<f32> {
+        let deviations: Vec<f32> = self
+            .notes
+            .iter()
+            .filter_map(|note| match note {
+                CheckedNote::Played { cents, .. } => Some(cents.abs()),
+                _ => None,
+            })
+            .collect();
+        (!deviations.is_empty()).then(|| deviations.iter().sum::<f32>() / deviations.len() as f32)
+    }
+
+    pub fn missed(&self) -> usize {
+        self.count(|note| matches!(note, CheckedNote::Missed { .. }))
+    }
+
+    pub fn extra(&self) -> usize {
+        self.count(|note| matches!(note, CheckedNote::Extra { .. }))
+    }
+
+    fn count(&self, f: impl Fn(&CheckedNote) -> bool) -> usize {
+        self.notes.iter().filter(|note| f(note)).count()
+    }
+
+    /// one line per note, followed by a summary
+    pub fn report(&self) -> String {
+        let mut res = String::new();
+        for note in self.notes.iter() {
+            let line = match note {
+                CheckedNote::Played { hole, start, cents } => {
+                    format!("{:>6} {:6.2}s {:+4.0} cents\n", hole, start, cents)
+                }
+                CheckedNote::Missed { hole } => format!("{:>6}         missed\n", hole),
+                CheckedNote::Extra { hole, start } => {
+                    format!("{:>6} {:6.2}s extra\n", hole, start)
+                }
+            };
+            res.push_str(&line);
+        }
+        if let Some(deviation) = self.average_deviation() {
+            res.push_str(&format!("average deviation: {:.0} cents\n", deviation));
+        }
+        res.push_str(&format!(
+            "missed notes: {}, extra notes: {}\n",
+            self.missed(),
+            self.extra()
+        ));
+        res
+    }
+}
+
+/// align the notes of a recording to a reference tab for a harp in `tuning` and `key`
+pub fn check_performance(
+    transcription: &Transcription,
+    tab: &str,
+    tuning: &str,
+    style: Style,
+    key: &str,
+) -> PerformanceCheck {
+    let expected = get_playback_notes_in_key(tab, tuning, style, key);
+    let detected = &transcription.notes;
+    let (n, m) = (expected.len(), detected.len());
+
+    // deviation in semitones of a detected note from an expected note
+    let deviation = |i: usize, j: usize| {
+        detected[j].index as f32 + detected[j].cents / 100.0 - expected[i].index as f32
+    };
+
+    // edit distance, with missed and extra notes costing 1, and a played note its deviation
+    let mut cost = vec![vec![0.0_f32; m + 1]; n + 1];
+    for i in 0..=n {
+        for j in 0..=m {
+            cost[i][j] = match (i, j) {
+                (0, _) => j as f32,
+                (_, 0) => i as f32,
+                _ => {
+                    let mut best = (cost[i - 1][j] + 1.0).min(cost[i][j - 1] + 1.0);
+                    let d = deviation(i - 1, j - 1).abs();
+                    if d <= MAX_DEVIATION {
+                        best = best.min(cost[i - 1][j - 1] + d);
+                    }
+                    best
+                }
+            };
+        }
+    }
+
+    let hole = |i: usize| tab[expected[i].span.clone()].to_string();
+    let detected_hole = |j: usize| detected[j].tab.clone().unwrap_or_else(|| "X".to_string());
+
+    let mut notes = Vec::new();
+    let (mut i, mut j) = (n, m);
+    while i > 0 || j > 0 {
+        if i > 0 && j > 0 {
+            let d = deviation(i - 1, j - 1);
+            if d.abs() <= MAX_DEVIATION && cost[i][j] == cost[i - 1][j - 1] + d.abs() {
+                notes.push(CheckedNote::Played {
+                    hole: hole(i - 1),
+                    start: detected[j - 1].start,
+                    cents: d * 100.0,
+                });
+                i -= 1;
+                j -= 1;
+                continue;
+            }
+        }
+        if i > 0 && cost[i][j] == cost[i - 1][j] + 1.0 {
+            notes.push(CheckedNote::Missed { hole: hole(i - 1) });
+            i -= 1;
+        } else {
+            notes.push(CheckedNote::Extra {
+                hole: detected_hole(j - 1),
+                start: detected[j - 1].start,
+            });
+            j -= 1;
+        }
+    }
+    notes.reverse();
+
+    PerformanceCheck { notes }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::synth::{render_with_intonation, Voice, SAMPLE_RATE};
+    use crate::temperament::{Intonation, Reed};
+    use crate::transcribe::transcribe;
+
+    #[test]
+    fn test_check_performance() {
+        // the -3'' bend played 30 cents flat, 5 left out and an extra 6 at the end
+        let draw_3 = Reed::of("-3");
+        let voices: Vec<Voice> = [(3, 0.0), (0, 0.3), (8, 0.6), (10, 0.9)]
+            .iter()
+            .map(|&(index, start)| Voice {
+                index,
+                reed: if index == 0 { draw_3 } else { None },
+                start,
+                duration: 0.3,
+                amplitude: 0.2,
+            })
+            .collect();
+        let mut intonation = Intonation::default();
+        intonation.reed_offsets.insert(draw_3.unwrap(), -30.0);
+        let samples = render_with_intonation(&voices, &intonation);
+        let transcription = transcribe(&samples, SAMPLE_RATE, "richter", "C", Style::Default);
+
+        let check = check_performance(
+            &transcription,
+            "4 -3'' 5 -5",
+            "richter",
+            Style::Default,
+            "C",
+        );
+        let holes: Vec<&str> = check
+            .notes
+            .iter()
+            .map(|note| match note {
+                CheckedNote::Played { hole, .. } => hole.as_str(),
+                CheckedNote::Missed { .. } => "missed",
+                CheckedNote::Extra { .. } => "extra",
+            })
+            .collect();
+        assert_eq!(holes, vec!["4", "-3''", "missed", "-5", "extra"]);
+
+        let CheckedNote::Played { cents, .. } = check.notes[1] else {
+            panic!("bend not played");
+        };
+        assert!((cents + 30.0).abs() < 5.0);
+        assert_eq!(check.missed(), 1);
+        assert_eq!(check.extra(), 1);
+        assert!(check.report().contains("missed notes: 1, extra notes: 1"));
+    }
+}
@@ -0,0 +1,464 @@
+use crate::{positions_to_semitones, transpose_tabs, Style, KEYS};
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// separates the header of a `.harptab` file from its body
+const HEADER_END: &str = "---";
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum HarpTabError {
+    #[error("line {line}: expected `field: value`, got `{text}`")]
+    InvalidHeaderLine { line: usize, text: String },
+    #[error("line {line}: unknown header field `{field}`")]
+    UnknownField { line: usize, field: String },
+    #[error("line {line}: `{value}` is not a valid {field}")]
+    InvalidValue {
+        line: usize,
+        field: String,
+        value: String,
+    },
+    #[error("line {line}: invalid directive `{text}`")]
+    InvalidDirective { line: usize, text: String },
+}
+
+/// what a `.harptab` file says about its tab, fields that aren't given are `None`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TabHeader {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    /// key of the harp
+    pub key: Option<String>,
+    pub position: Option<u32>,
+    pub tuning: Option<String>,
+    pub style: Option<Style>,
+    /// beats per minute
+    pub tempo: Option<f64>,
+}
+
+/// a line in the body of a `.harptab` file starting with `%`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Directive {
+    /// `%section chorus`, resets the shift of the following lines
+    Section(String),
+    /// `%transpose +12`, semitones added to the following lines
+    Transpose(i32),
+    /// `%octave -1`, octaves added to the following lines
+    Octave(i32),
+    /// `%position 3`, the position the following lines are transposed to
+    Position(u32),
+    /// `%style harpsurgery`, the style the following lines are written in
+    Style(Style),
+    /// `%% text`, a comment that doesn't change anything
+    Comment(String),
+}
+
+impl Directive {
+    /// parse a directive line, `None` if `line` isn't a directive
+    pub fn parse(line: &str) -> Option<Result<Self, String>> {
+        let directive = line.trim().strip_prefix('%')?;
+        if let Some(comment) = directive.strip_prefix('%') {
+            return Some(Ok(Directive::Comment(comment.trim().to_string())));
+        }
+        let (name, value) = directive
+            .split_once(char::is_whitespace)
+            .map_or((directive, ""), |(name, value)| (name, value.trim()));
+        let parsed = match name {
+            "section" => Some(Directive::Section(value.to_string())),
+            "transpose" => value.parse::<i32>().ok().map(Directive::Transpose),
+            "octave" => value.parse::<i32>().ok().map(Directive::Octave),
+            "position" => value
+                .parse::<u32>()
+                .ok()
+                .filter(|p| (1..=12).contains(p))
+                .map(Directive::Position),
+            "style" => Style::from_str(value).ok().map(Directive::Style),
+            _ => None,
+        };
+        Some(parsed.ok_or_else(|| line.trim().to_string()))
+    }
+}
+
+impl fmt::Display for Directive {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Directive::Section(name) => write!(f, "%section {}", name),
+            Directive::Transpose(semitones) => write!(f, "%transpose {:+}", semitones),
+            Directive::Octave(octaves) => write!(f, "%octave {:+}", octaves),
+            Directive::Position(position) => write!(f, "%position {}", position),
+            Directive::Style(style) => write!(f, "%style {}", style),
+            Directive::Comment(text) => write!(f, "%% {}", text),
+        }
+    }
+}
+
+/// how lines are transposed relative to the whole tab, following the directives before them
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LineShift {
+    pub semitones: i32,
+    pub octaves: i32,
+    pub position: Option<u32>,
+}
+
+impl LineShift {
+    /// semitones to transpose by, for a tab in `from_position` that is otherwise transposed by `semitones`
+    pub fn total(&self, semitones: i32, from_position: u32) -> i32 {
+        let base = match self.position {
+            Some(position) => positions_to_semitones(from_position as i32, position as i32, 0),
+            None => semitones,
+        };
+        base + self.semitones + 12 * self.octaves
+    }
+
+    pub(crate) fn apply(&mut self, directive: &Directive) {
+        match directive {
+            Directive::Section(_) => *self = LineShift::default(),
+            Directive::Transpose(semitones) => self.semitones = *semitones,
+            Directive::Octave(octaves) => self.octaves = *octaves,
+            Directive::Position(position) => self.position = Some(*position),
+            Directive::Style(_) | Directive::Comment(_) => (),
+        }
+    }
+}
+
+/// what a line of a tab body is, after the directives before it
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum LineKind {
+    Directive(Directive),
+    /// a line starting with `%` that isn't a valid directive
+    InvalidDirective(String),
+    /// tab shifted by the directives before it, written in their style
+    Tab(LineShift, Style),
+}
+
+/// a line of a tab body
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct BodyLine<'a> {
+    /// byte offset in the body
+    pub(crate) start: usize,
+    /// without the line break
+    pub(crate) text: &'a str,
+    pub(crate) kind: LineKind,
+}
+
+/// the lines of a tab body written in `style`, applying every directive to the lines after it
+pub(crate) fn body_lines(body: &str, style: Style) -> Vec<BodyLine<'_>> {
+    let (mut shift, mut style) = (LineShift::default(), style);
+    let mut offset = 0;
+    body.split_inclusive('\n')
+        .map(|line| {
+            let start = offset;
+            offset += line.len();
+            let text = line.trim_end_matches('\n').trim_end_matches('\r');
+            let kind = match Directive::parse(text) {
+                Some(Ok(directive)) => {
+                    shift.apply(&directive);
+                    if let Directive::Style(new_style) = directive {
+                        style = new_style;
+                    }
+                    LineKind::Directive(directive)
+                }
+                Some(Err(text)) => LineKind::InvalidDirective(text),
+                None => LineKind::Tab(shift, style),
+            };
+            BodyLine { start, text, kind }
+        })
+        .collect()
+}
+
+/// the shift of every line of a tab body, directive lines get the shift they set
+pub fn line_shifts(body: &str) -> Vec<LineShift> {
+    let mut shift = LineShift::default();
+    body.lines()
+        .map(|line| {
+            if let Some(Ok(directive)) = Directive::parse(line) {
+                shift.apply(&directive);
+            }
+            shift
+        })
+        .collect()
+}
+
+/// a tab along with its header, as stored in `.harptab` files
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HarpTab {
+    pub header: TabHeader,
+    pub body: String,
+}
+
+impl FromStr for HarpTab {
+    type Err = HarpTabError;
+
+    /// the header ends at a `---` line, text without one is a body without header
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lines: Vec<&str> = s.lines().collect();
+        let Some(header_end) = lines.iter().position(|l| l.trim() == HEADER_END) else {
+            let tab = HarpTab {
+                header: TabHeader::default(),
+                body: s.to_string(),
+            };
+            tab.directives()?;
+            return Ok(tab);
+        };
+
+        let mut header = TabHeader::default();
+        for (i, text) in lines[..header_end].iter().enumerate() {
+            let line = i + 1;
+            let text = text.trim();
+            if text.is_empty() || text.starts_with('#') {
+                continue;
+            }
+            let Some((field, value)) = text.split_once(':') else {
+                return Err(HarpTabError::InvalidHeaderLine {
+                    line,
+                    text: text.to_string(),
+                });
+            };
+            let (field, value) = (field.trim(), value.trim());
+            let invalid = || HarpTabError::InvalidValue {
+                line,
+                field: field.to_string(),
+                value: value.to_string(),
+            };
+            match field {
+                "title" => header.title = Some(value.to_string()),
+                "artist" => header.artist = Some(value.to_string()),
+                "key" => {
+                    let key = KEYS.iter().find(|k| **k == value).ok_or_else(invalid)?;
+                    header.key = Some(key.to_string());
+                }
+                "position" => {
+                    let position = value.parse::<u32>().ok().filter(|p| (1..=12).contains(p));
+                    header.position = Some(position.ok_or_else(invalid)?);
+                }
+                "tuning" => {
+                    if !harptool::tunings::get_tunings().contains_key(value) {
+                        return Err(invalid());
+                    }
+                    header.tuning = Some(value.to_string());
+                }
+                "style" => header.style = Some(Style::from_str(value).map_err(|_| invalid())?),
+                "tempo" => {
+                    let tempo = value.parse::<f64>().ok().filter(|t| *t > 0.0);
+                    header.tempo = Some(tempo.ok_or_else(invalid)?);
+                }
+                _ => {
+                    return Err(HarpTabError::UnknownField {
+                        line,
+                        field: field.to_string(),
+                    })
+                }
+            }
+        }
+
+        let body: Vec<&str> = lines[header_end + 1..].to_vec();
+        let tab = HarpTab {
+            header,
+            body: body.join("\n") + if body.is_empty() { "" } else { "\n" },
+        };
+        // report invalid directives with their line in the file
+        tab.directives().map_err(|e| match e {
+            HarpTabError::InvalidDirective { line, text } => HarpTabError::InvalidDirective {
+                line: line + header_end + 1,
+                text,
+            },
+            e => e,
+        })?;
+        Ok(tab)
+    }
+}
+
+impl fmt::Display for HarpTab {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let header = &self.header;
+        if let Some(title) = &header.title {
+            writeln!(f, "title: {}", title)?;
+        }
+        if let Some(artist) = &header.artist {
+            writeln!(f, "artist: {}", artist)?;
+        }
+        if let Some(key) = &header.key {
+            writeln!(f, "key: {}", key)?;
+        }
+        if let Some(position) = header.position {
+            writeln!(f, "position: {}", position)?;
+        }
+        if let Some(tuning) = &header.tuning {
+            writeln!(f, "tuning: {}", tuning)?;
+        }
+        if let Some(style) = header.style {
+            writeln!(f, "style: {}", style)?;
+        }
+        if let Some(tempo) = header.tempo {
+            writeln!(f, "tempo: {}", tempo)?;
+        }
+        writeln!(f, "{}", HEADER_END)?;
+        write!(f, "{}", self.body)
+    }
+}
+
+impl HarpTab {
+    /// the directives in the body along with their line number (starting at 1)
+    pub fn directives(&self) -> Result<Vec<(usize, Directive)>, HarpTabError> {
+        self.body
+            .lines()
+            .enumerate()
+            .filter_map(|(i, line)| {
+                Directive::parse(line).map(|directive| {
+                    directive
+                        .map(|d| (i + 1, d))
+                        .map_err(|text| HarpTabError::InvalidDirective { line: i + 1, text })
+                })
+            })
+            .collect()
+    }
+}
+
+/// transpose a tab body in `from_position`, applying directives from their line onward.
+/// `%transpose`, `%octave` and `%position` directives are left out of the result, since it is already transposed
+pub fn transpose_body(
+    body: &str,
+    semitones: i32,
+    from_position: u32,
+    keep_errors: bool,
+    style: Style,
+    input_tuning: &str,
+    output_tuning: &str,
+) -> (String, Vec<String>) {
+    let mut res = String::new();
+    let mut errors = Vec::new();
+    let mut chunk = String::new();
+    let (mut shift, mut chunk_style) = (LineShift::default(), style);
+
+    let flush = |res: &mut String,
+                 errors: &mut Vec<String>,
+                 chunk: &mut String,
+                 shift: LineShift,
+                 style| {
+        if chunk.is_empty() {
+            return;
+        }
+        let (tabs, mut chunk_errors) = transpose_tabs(
+            std::mem::take(chunk),
+            shift.total(semitones, from_position),
+            keep_errors,
+            style,
+            input_tuning,
+            output_tuning,
+        );
+        res.push_str(&tabs);
+        errors.append(&mut chunk_errors);
+    };
+
+    for line in body_lines(body, style) {
+        match line.kind {
+            LineKind::Directive(directive) => {
+                flush(&mut res, &mut errors, &mut chunk, shift, chunk_style);
+                if let Directive::Style(_) | Directive::Section(_) | Directive::Comment(_) =
+                    directive
+                {
+                    res.push_str(&format!("{}\n", directive));
+                }
+            }
+            LineKind::InvalidDirective(text) => {
+                flush(&mut res, &mut errors, &mut chunk, shift, chunk_style);
+                errors.push(text.clone());
+                if keep_errors {
+                    res.push_str(&format!("{}\n", text));
+                }
+            }
+            LineKind::Tab(line_shift, line_style) => {
+                (shift, chunk_style) = (line_shift, line_style);
+                chunk.push_str(line.text);
+                chunk.push('\n');
+            }
+        }
+    }
+    flush(&mut res, &mut errors, &mut chunk, shift, chunk_style);
+
+    (res, errors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SONG: &str = "title: Oh Susanna
+key: C
+position: 2
+tuning: richter
+style: default
+tempo: 100
+---
+%section verse
+-1 -2 3 4 4
+%transpose +12
+-1 -2 3 4 4
+";
+
+    #[test]
+    fn test_parse_harptab() {
+        let tab = HarpTab::from_str(SONG).unwrap();
+        assert_eq!(tab.header.title.as_deref(), Some("Oh Susanna"));
+        assert_eq!(tab.header.position, Some(2));
+        assert_eq!(tab.header.style, Some(Style::Default));
+        assert_eq!(tab.header.tempo, Some(100.0));
+        assert_eq!(tab.header.artist, None);
+        assert_eq!(
+            tab.directives().unwrap(),
+            vec![
+                (1, Directive::Section(String::from("verse"))),
+                (3, Directive::Transpose(12))
+            ]
+        );
+        assert_eq!(HarpTab::from_str(&tab.to_string()), Ok(tab));
+
+        assert_eq!(
+            HarpTab::from_str("key: H\n---\n"),
+            Err(HarpTabError::InvalidValue {
+                line: 1,
+                field: String::from("key"),
+                value: String::from("H")
+            })
+        );
+        assert_eq!(
+            HarpTab::from_str("title: a\n---\n%octave\n").unwrap_err(),
+            HarpTabError::InvalidDirective {
+                line: 3,
+                text: String::from("%octave")
+            }
+        );
+
+        let tab = HarpTab::from_str("4 5 6\n").unwrap();
+        assert_eq!(tab.header, TabHeader::default());
+        assert_eq!(tab.body, "4 5 6\n");
+    }
+
+    #[test]
+    fn test_transpose_body() {
+        let tab = HarpTab::from_str(SONG).unwrap();
+        let (res, errors) =
+            transpose_body(&tab.body, 0, 2, false, Style::Default, "richter", "richter");
+        assert_eq!(res, "%section verse\n-1 -2 -2 4 4 \n-4 6 6 7 7 \n");
+        assert!(errors.is_empty());
+
+        let body = "4 5\n%style harpsurgery\n4B 5B\n%%  up high\n";
+        let (res, _) = transpose_body(body, 12, 1, false, Style::Default, "richter", "richter");
+        assert_eq!(res, "7 8 \n%style harpsurgery\n7B 8B \n%% up high\n");
+    }
+
+    #[test]
+    fn test_line_shifts() {
+        // verse an octave down, chorus where it is, then the bridge in 3rd position
+        let body = "%section verse\n%octave -1\n7 8\n%section chorus\n7 8\n%position 3\n7 8\n";
+        let shifts = line_shifts(body);
+        assert_eq!(shifts[2].octaves, -1);
+        assert_eq!(shifts[4], LineShift::default());
+        assert_eq!(shifts[6].position, Some(3));
+        assert_eq!(shifts[6].total(0, 2), 7);
+
+        let (res, errors) = transpose_body(body, 0, 2, false, Style::Default, "richter", "richter");
+        assert_eq!(res, "%section verse\n4 5 \n%section chorus\n7 8 \n9 10' \n");
+        assert!(errors.is_empty());
+    }
+}
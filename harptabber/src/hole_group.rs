@@ -0,0 +1,277 @@
+use crate::{change_tab_style_single, fix_enharmonics, Style};
+use regex::Regex;
+use std::ops::Range;
+
+/// how a group of holes was written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroupNotation {
+    /// `(4 5 6)`
+    Parens,
+    /// `[1 4]`, a tongue-blocked split
+    Brackets,
+    /// `-2/-3`
+    Slash,
+    /// `456` or `-456`
+    Run,
+}
+
+/// several holes played at once, e.g. a chord or a split
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HoleGroup {
+    pub notation: GroupNotation,
+    pub holes: Vec<String>,
+}
+
+impl HoleGroup {
+    /// parse a group of holes, `None` if `token` isn't a group of notes on a harp with `notes`
+    pub fn parse(token: &str, notes: &[String], duplicated_notes: &[String]) -> Option<Self> {
+        lazy_static! {
+            static ref RUN: Regex = Regex::new(r"^(?P<dir>-?)(?P<holes>[1-9]{2,})$").unwrap();
+        }
+
+        let split = |s: &str| s.split_whitespace().map(String::from).collect();
+        let (notation, holes): (GroupNotation, Vec<String>) =
+            if let Some(inner) = token.strip_prefix('(').and_then(|t| t.strip_suffix(')')) {
+                (GroupNotation::Parens, split(inner))
+            } else if let Some(inner) = token.strip_prefix('[').and_then(|t| t.strip_suffix(']')) {
+                (GroupNotation::Brackets, split(inner))
+            } else if token.contains('/') {
+                (
+                    GroupNotation::Slash,
+                    token.split('/').map(String::from).collect(),
+                )
+            } else if let Some(caps) = RUN.captures(token) {
+                let holes = caps["holes"]
+                    .chars()
+                    .map(|hole| format!("{}{}", &caps["dir"], hole))
+                    .collect();
+                (GroupNotation::Run, holes)
+            } else {
+                return None;
+            };
+
+        let valid = holes.len() >= 2
+            && holes.iter().all(|hole| {
+                notes
+                    .iter()
+                    .any(|n| n == fix_enharmonics(hole, duplicated_notes))
+            });
+        valid.then_some(HoleGroup { notation, holes })
+    }
+
+    /// write holes in the notation of this group
+    pub fn format(&self, holes: &[String]) -> String {
+        lazy_static! {
+            static ref SINGLE_DIGIT: Regex = Regex::new(r"^-?[1-9]$").unwrap();
+        }
+
+        match self.notation {
+            GroupNotation::Parens => format!("({})", holes.join(" ")),
+            GroupNotation::Brackets => format!("[{}]", holes.join(" ")),
+            GroupNotation::Slash => holes.join("/"),
+            GroupNotation::Run => {
+                let draw = holes[0].starts_with('-');
+                let is_run = holes
+                    .iter()
+                    .all(|h| SINGLE_DIGIT.is_match(h) && h.starts_with('-') == draw);
+                if is_run {
+                    let digits: String = holes.iter().map(|h| h.trim_start_matches('-')).collect();
+                    format!("{}{}", if draw { "-" } else { "" }, digits)
+                } else {
+                    format!("({})", holes.join(" "))
+                }
+            }
+        }
+    }
+
+    /// transpose every hole, choosing between duplicated notes so that the group stays playable.
+    /// `output_notes` and `output_duplicated_notes` are in the default style.
+    /// groups that can't be played are marked with a leading `X`
+    pub fn transpose(
+        &self,
+        input_notes: &[String],
+        input_duplicated_notes: &[String],
+        output_notes: &[String],
+        output_duplicated_notes: &[String],
+        semitones: i32,
+        style: Style,
+    ) -> String {
+        let candidates: Vec<Vec<String>> = self
+            .holes
+            .iter()
+            .map(|hole| {
+                let hole = fix_enharmonics(hole, input_duplicated_notes);
+                let pos = input_notes.iter().position(|n| n == hole).unwrap() as i32 + semitones;
+                let note = usize::try_from(pos)
+                    .ok()
+                    .and_then(|pos| output_notes.get(pos))
+                    .map_or("X", |n| n.as_str());
+
+                let mut candidates = vec![note.to_string()];
+                for pair in output_duplicated_notes.chunks(2) {
+                    if pair.len() == 2 && pair[1] == note {
+                        candidates.push(pair[0].clone());
+                    }
+                }
+                candidates
+            })
+            .collect();
+
+        let combinations = candidates.iter().fold(vec![Vec::new()], |acc, options| {
+            acc.iter()
+                .flat_map(|holes: &Vec<String>| {
+                    options.iter().map(move |option| {
+                        let mut holes = holes.clone();
+                        holes.push(option.clone());
+                        holes
+                    })
+                })
+                .collect::<Vec<Vec<String>>>()
+        });
+        let playable = combinations
+            .iter()
+            .find(|holes| is_playable_group(holes, self.notation));
+        let holes = playable.unwrap_or(&combinations[0]);
+
+        let holes: Vec<String> = holes
+            .iter()
+            .map(|hole| change_tab_style_single(hole, style))
+            .collect();
+        let mark = if playable.is_some() { "" } else { "X" };
+        format!("{}{}", mark, self.format(&holes))
+    }
+}
+
+/// whether holes written in the default style can be played at once:
+/// unbent notes in the same direction, next to each other or (for splits) at most 3 holes apart
+pub fn is_playable_group(holes: &[String], notation: GroupNotation) -> bool {
+    lazy_static! {
+        static ref NATURAL: Regex = Regex::new(r"^(?P<dir>-?)(?P<hole>\d{1,2})$").unwrap();
+    }
+
+    let Some(parsed) = holes
+        .iter()
+        .map(|hole| {
+            NATURAL.captures(hole).map(|caps| {
+                (
+                    !caps["dir"].is_empty(),
+                    caps["hole"].parse::<u32>().unwrap(),
+                )
+            })
+        })
+        .collect::<Option<Vec<(bool, u32)>>>()
+    else {
+        return false;
+    };
+    if parsed.iter().any(|(draw, _)| *draw != parsed[0].0) {
+        return false;
+    }
+
+    let mut numbers: Vec<u32> = parsed.iter().map(|(_, hole)| *hole).collect();
+    numbers.sort_unstable();
+    numbers.dedup();
+    if numbers.len() != holes.len() {
+        return false;
+    }
+    let span = numbers[numbers.len() - 1] - numbers[0];
+    match notation {
+        GroupNotation::Brackets => span <= 3,
+        _ => span as usize == numbers.len() - 1,
+    }
+}
+
+/// split text into notes and groups of holes along with their byte ranges,
+/// keeping bracketed groups on a single line together. an opening bracket that isn't
+/// closed on its line only starts a token of its own
+pub(crate) fn tokens(text: &str) -> Vec<(Range<usize>, &str)> {
+    let mut res = Vec::new();
+    let mut chars = text.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        if c.is_whitespace() {
+            continue;
+        }
+        let close = match c {
+            '(' => Some(')'),
+            '[' => Some(']'),
+            _ => None,
+        };
+        let rest = &text[start..];
+        let line = &rest[..rest.find('\n').unwrap_or(rest.len())];
+        let group_end = close.and_then(|close| line.find(close).map(|i| i + 1));
+        let len = group_end.unwrap_or_else(|| line.find(char::is_whitespace).unwrap_or(line.len()));
+        let end = start + len;
+        while chars.peek().is_some_and(|&(i, _)| i < end) {
+            chars.next();
+        }
+        res.push((start..end, &text[start..end]));
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tuning_to_notes_in_order;
+
+    #[test]
+    fn test_tokens() {
+        let text = "-2 (4 5 6)  [1 4]\n-2/-3 (4";
+        let tokens: Vec<&str> = tokens(text).into_iter().map(|(_, t)| t).collect();
+        assert_eq!(tokens, vec!["-2", "(4 5 6)", "[1 4]", "-2/-3", "(4"]);
+        assert_eq!(super::tokens(text)[1].0, 3..10);
+
+        // an unclosed group doesn't swallow the rest of the line
+        let tokens: Vec<&str> = super::tokens("(4 5 6\n[1 4] -2)")
+            .into_iter()
+            .map(|(_, t)| t)
+            .collect();
+        assert_eq!(tokens, vec!["(4", "5", "6", "[1 4]", "-2)"]);
+    }
+
+    #[test]
+    fn test_parse_hole_group() {
+        let (notes, duplicated) = tuning_to_notes_in_order("richter");
+        let group = HoleGroup::parse("-456", &notes, &duplicated).unwrap();
+        assert_eq!(group.notation, GroupNotation::Run);
+        assert_eq!(group.holes, vec!["-4", "-5", "-6"]);
+        assert_eq!(group.format(&group.holes), "-456");
+
+        let group = HoleGroup::parse("-2/-3'", &notes, &duplicated).unwrap();
+        assert_eq!(group.holes, vec!["-2", "-3'"]);
+        assert_eq!(HoleGroup::parse("(4 Y)", &notes, &duplicated), None);
+        assert_eq!(HoleGroup::parse("(4)", &notes, &duplicated), None);
+    }
+
+    #[test]
+    fn test_is_playable_group() {
+        let holes = |h: &[&str]| h.iter().map(|s| s.to_string()).collect::<Vec<String>>();
+        assert!(is_playable_group(
+            &holes(&["4", "5", "6"]),
+            GroupNotation::Parens
+        ));
+        assert!(!is_playable_group(
+            &holes(&["4", "6"]),
+            GroupNotation::Parens
+        ));
+        assert!(!is_playable_group(
+            &holes(&["-4", "5"]),
+            GroupNotation::Slash
+        ));
+        assert!(!is_playable_group(
+            &holes(&["-4'", "-5"]),
+            GroupNotation::Slash
+        ));
+        assert!(is_playable_group(
+            &holes(&["1", "4"]),
+            GroupNotation::Brackets
+        ));
+        assert!(!is_playable_group(
+            &holes(&["1", "5"]),
+            GroupNotation::Brackets
+        ));
+        assert!(!is_playable_group(
+            &holes(&["1", "6"]),
+            GroupNotation::Brackets
+        ));
+    }
+}
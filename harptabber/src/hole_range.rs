@@ -0,0 +1,218 @@
+use crate::{get_playback_notes, tuning_to_notes_in_order, Style, Tab, Transposer};
+use regex::Regex;
+use std::str::FromStr;
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum HoleRangeError {
+    #[error("`{0}` is not a valid hole range (expected something like 4-7, 4..7, -3.. or ..6)")]
+    InvalidRange(String),
+}
+
+/// one end of a hole range
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RangeBound {
+    /// any note on this hole, e.g. `4`
+    Hole(u32),
+    /// a note (in the default style), limiting the pitch, e.g. `-3`
+    Note(String),
+}
+
+/// the part of the harp a tab should stay in
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HoleRange {
+    pub low: Option<RangeBound>,
+    pub high: Option<RangeBound>,
+}
+
+impl FromStr for HoleRange {
+    type Err = HoleRangeError;
+
+    /// `4-7` or `4..7` for holes 4 to 7, `-3..` for nothing below 3 draw, `..6` for nothing above hole 6
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        lazy_static! {
+            static ref HOLES: Regex = Regex::new(r"^(?P<low>\d{1,2})-(?P<high>\d{1,2})$").unwrap();
+            static ref BOUND: Regex = Regex::new(r"^-?\d{1,2}'*$").unwrap();
+        }
+
+        let invalid = || HoleRangeError::InvalidRange(s.to_string());
+        let bound = |b: &str| -> Result<Option<RangeBound>, HoleRangeError> {
+            match b {
+                "" => Ok(None),
+                b if !BOUND.is_match(b) => Err(invalid()),
+                b => Ok(Some(match b.parse::<u32>() {
+                    Ok(hole) => RangeBound::Hole(hole),
+                    Err(_) => RangeBound::Note(b.to_string()),
+                })),
+            }
+        };
+
+        let range = if let Some(caps) = HOLES.captures(s) {
+            HoleRange {
+                low: bound(&caps["low"])?,
+                high: bound(&caps["high"])?,
+            }
+        } else {
+            let (low, high) = s.split_once("..").ok_or_else(invalid)?;
+            HoleRange {
+                low: bound(low)?,
+                high: bound(high)?,
+            }
+        };
+        if range.low.is_none() && range.high.is_none() {
+            return Err(invalid());
+        }
+        Ok(range)
+    }
+}
+
+impl HoleRange {
+    /// positions in the notes (in the default style) of a tuning that are in this range
+    fn positions(&self, notes: &[String]) -> (usize, usize) {
+        lazy_static! {
+            static ref HOLE: Regex = Regex::new(r"^-?(?P<hole>\d{1,2})").unwrap();
+        }
+        let hole = |note: &String| {
+            HOLE.captures(note)
+                .map(|caps| caps["hole"].parse::<u32>().unwrap())
+        };
+        let find = |bound: &RangeBound, last: bool| {
+            let matches = |note: &String| match bound {
+                RangeBound::Hole(h) => hole(note) == Some(*h),
+                RangeBound::Note(n) => note == n,
+            };
+            let position = if last {
+                notes.iter().rposition(matches)
+            } else {
+                notes.iter().position(matches)
+            };
+            // holes that aren't on the harp limit nothing
+            position.unwrap_or(if last { notes.len() - 1 } else { 0 })
+        };
+
+        let low = self.low.as_ref().map_or(0, |bound| find(bound, false));
+        let high = self
+            .high
+            .as_ref()
+            .map_or(notes.len() - 1, |bound| find(bound, true));
+        (low, high)
+    }
+
+    /// whether every note of a tab (and every hole of its chords) is in this range
+    pub fn contains_tab(&self, tab: &str, tuning: &str, style: Style) -> bool {
+        let (notes, _) = tuning_to_notes_in_order(tuning);
+        let (low, high) = self.positions(&notes);
+        get_playback_notes(tab, tuning, style).iter().all(|note| {
+            std::iter::once(&note.index)
+                .chain(note.chord.iter())
+                .all(|index| (low as i32..=high as i32).contains(&(index + 9)))
+        })
+    }
+
+    /// how far the notes of a tab are from the middle of this range on average, in semitones
+    pub fn distance_from_center(&self, tab: &str, tuning: &str, style: Style) -> f32 {
+        let (notes, _) = tuning_to_notes_in_order(tuning);
+        let (low, high) = self.positions(&notes);
+        let center = (low + high) as f32 / 2.0;
+        let played = get_playback_notes(tab, tuning, style);
+        if played.is_empty() {
+            return 0.0;
+        }
+        let sum: f32 = played
+            .iter()
+            .map(|note| (note.index as f32 + 9.0 - center).abs())
+            .sum();
+        sum / played.len() as f32
+    }
+}
+
+/// the octave of `semitones` closest to it that keeps a tab playable and in `range`
+pub fn fit_octave(
+    tab: &str,
+    semitones: i32,
+    range: &HoleRange,
+    style: Style,
+    input_tuning: &str,
+    output_tuning: &str,
+) -> Option<i32> {
+    let transposer = Transposer::new(tab, style, input_tuning, output_tuning);
+    let mut candidates: Vec<i32> = (-3..=3).map(|octaves| semitones + 12 * octaves).collect();
+    candidates.sort_by_key(|candidate| (candidate - semitones).abs());
+    candidates.into_iter().find(|&candidate| {
+        let (transposed, _) = transposer.transpose(candidate, false);
+        transposed.is_playable() && range.contains_tab(&transposed, output_tuning, style)
+    })
+}
+
+/// keep the playable positions that fit a range, the ones closest to its middle first
+pub fn filter_positions_by_range(
+    positions: &[(u32, i32)],
+    tab: &str,
+    range: &HoleRange,
+    style: Style,
+    input_tuning: &str,
+    output_tuning: &str,
+) -> Vec<(u32, i32)> {
+    let transposer = Transposer::new(tab, style, input_tuning, output_tuning);
+    let mut fitting: Vec<((u32, i32), f32)> = positions
+        .iter()
+        .filter_map(|&(position, semitones)| {
+            let (transposed, _) = transposer.transpose(semitones, false);
+            range
+                .contains_tab(&transposed, output_tuning, style)
+                .then(|| {
+                    let distance = range.distance_from_center(&transposed, output_tuning, style);
+                    ((position, semitones), distance)
+                })
+        })
+        .collect();
+    fitting.sort_by(|(_, a), (_, b)| a.total_cmp(b));
+    fitting.into_iter().map(|(pair, _)| pair).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::get_playable_positions;
+
+    #[test]
+    fn test_parse_hole_range() {
+        let range = HoleRange::from_str("4-7").unwrap();
+        assert_eq!(range.low, Some(RangeBound::Hole(4)));
+        assert_eq!(range.high, Some(RangeBound::Hole(7)));
+        let range = HoleRange::from_str("-3..").unwrap();
+        assert_eq!(range.low, Some(RangeBound::Note(String::from("-3"))));
+        assert_eq!(range.high, None);
+        assert!(HoleRange::from_str("..").is_err());
+        assert!(HoleRange::from_str("4").is_err());
+    }
+
+    #[test]
+    fn test_hole_range() {
+        let range = HoleRange::from_str("4-7").unwrap();
+        assert!(range.contains_tab("4 -4 5 -7 (6 7)", "richter", Style::Default));
+        assert!(!range.contains_tab("-3 4", "richter", Style::Default));
+        assert!(!range.contains_tab("7 8", "richter", Style::Default));
+        // the 3 draw bends are below it
+        let range = HoleRange::from_str("-3..").unwrap();
+        assert!(range.contains_tab("-3 4", "richter", Style::Default));
+        assert!(!range.contains_tab("-3' 4", "richter", Style::Default));
+
+        let range = HoleRange::from_str("4-7").unwrap();
+        let res = fit_octave("1 2 3", 0, &range, Style::Default, "richter", "richter");
+        assert_eq!(res, Some(12));
+
+        let tab = "4 5 6 7";
+        let positions = get_playable_positions(tab, 1, "richter", "richter", Style::Default, true);
+        let res = filter_positions_by_range(
+            &positions,
+            tab,
+            &range,
+            Style::Default,
+            "richter",
+            "richter",
+        );
+        assert!(res.len() < positions.len());
+        assert_eq!(res[0], (1, 0));
+    }
+}
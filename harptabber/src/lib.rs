@@ -1,17 +1,94 @@
 use regex::Regex;
+use serde::{Serialize, Serializer};
 use std::collections::BTreeMap;
+use std::fmt;
 use std::fs;
+use std::ops::Range;
+use std::str::FromStr;
 use thiserror::Error;
+use transposer::TuningTable;
 
 #[macro_use]
 extern crate lazy_static;
 
 #[cfg(not(target_arch = "wasm32"))]
 mod audio;
+mod backing;
+mod batch;
+mod check;
+mod harptab;
+mod hole_group;
+mod hole_range;
+mod metronome;
+mod note_input;
+mod octave_split;
+mod ornament;
+mod playback;
+mod practice;
+mod quiz;
+mod report;
+mod synth;
+mod technique;
+mod temperament;
+mod transcribe;
+mod transposer;
+#[cfg(target_arch = "wasm32")]
+mod web_audio;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use audio::RodioOutput;
+pub use backing::{
+    backing_voices, render_backing_track, song_key, BackingError, BackingOptions, Chord,
+    Progression,
+};
+pub use batch::{
+    collect_tab_files, transpose_batch, transpose_file, BatchError, BatchFile, BatchOptions,
+    BatchOutput, BatchResult, BatchSummary, TransposedFile, TAB_EXTENSIONS,
+};
+pub use check::{check_performance, CheckedNote, PerformanceCheck};
+pub use harptab::{
+    line_shifts, transpose_body, Directive, HarpTab, HarpTabError, LineShift, TabHeader,
+};
+pub use hole_group::{is_playable_group, GroupNotation, HoleGroup};
+pub use hole_range::{
+    filter_positions_by_range, fit_octave, HoleRange, HoleRangeError, RangeBound,
+};
+pub use metronome::{Metronome, TimeSignature, TimeSignatureError};
+pub use note_input::{
+    degrees_to_tab, input_to_tab, note_names_to_tab, InputFormat, InputFormatError,
+};
+pub use octave_split::{split_octaves, OctaveSplit, Phrase};
+pub use ornament::{Ornament, OrnamentedNote, ARTICULATION_WORDS};
+pub use playback::{AudioOutput, PlaybackEvent, PlaybackNote, Player, NOTE_DURATION};
+pub use practice::{PracticeOptions, PracticeSession};
+pub use quiz::{AnswerKind, Question, QuestionKind, QuizError, QuizOptions, QuizSession, Score};
+pub use report::{
+    to_json, OutputFormat, OutputFormatError, PositionReport, TokenError, TranspositionReport,
+};
+pub use synth::{
+    index_to_frequency, melody_voices, render, render_with_intonation, write_wav, Voice,
+    SAMPLE_RATE,
+};
+pub use technique::{
+    note_difficulty, tab_difficulty, BendLimits, Holes, TechniqueError, TechniqueInventory,
+    TechniqueProfile,
+};
+pub use temperament::{Intonation, Reed, ReedOffsets, Temperament, TemperamentError};
+pub use transcribe::{
+    detect_pitch, read_wav, transcribe, TranscribedNote, Transcription, TranscriptionError,
+};
+pub use transposer::{TransposedToken, Transposer};
+#[cfg(target_arch = "wasm32")]
+pub use web_audio::WebAudioOutput;
+
+/// the audio output used on the current platform
 #[cfg(not(target_arch = "wasm32"))]
-use rodio::{OutputStream, Sink};
+pub type PlatformOutput = RodioOutput;
+/// the audio output used on the current platform
+#[cfg(target_arch = "wasm32")]
+pub type PlatformOutput = WebAudioOutput;
 
-#[derive(PartialEq, Eq, Copy, Clone)]
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
 pub enum Style {
     Default,
     Harpsurgery,
@@ -20,6 +97,50 @@ pub enum Style {
     DrawDefault,
 }
 
+#[derive(Error, Debug, PartialEq, Eq)]
+#[error("`{0}` is not a valid tab style (expected harpsurgery, b-bends, plus, draw or default)")]
+pub struct StyleError(String);
+
+impl FromStr for Style {
+    type Err = StyleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "default" => Ok(Style::Default),
+            "harpsurgery" => Ok(Style::Harpsurgery),
+            "b-bends" => Ok(Style::BBends),
+            "plus" => Ok(Style::Plus),
+            "draw" => Ok(Style::DrawDefault),
+            _ => Err(StyleError(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for Style {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Style::Default => "default",
+            Style::Harpsurgery => "harpsurgery",
+            Style::BBends => "b-bends",
+            Style::Plus => "plus",
+            Style::DrawDefault => "draw",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl Serialize for Style {
+    /// by the same name as on the command line
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// keys harps are made in
+pub const KEYS: [&str; 12] = [
+    "C", "Db", "D", "Eb", "E", "F", "F#", "G", "Ab", "A", "Bb", "B",
+];
+
 #[derive(Error, Debug, PartialEq)]
 pub enum TransposeError<'a> {
     #[error("`{0}` is not a valid note")]
@@ -62,19 +183,87 @@ pub fn positions_to_semitones(from_position: i32, to_position: i32, octave_shift
     (diff * 7).rem_euclid(12) + 12 * octave_shift
 }
 
+/// exit code when some tokens of the input aren't notes on the input harp
+pub const EXIT_INVALID_NOTES: i32 = 1;
+/// exit code when some notes aren't on the output harp, added to `EXIT_INVALID_NOTES` if both happen
+pub const EXIT_UNPLAYABLE_NOTES: i32 = 2;
+
+/// the exit code for a transposition with invalid or unplayable notes, 0 without either
+pub fn exit_code(invalid_notes: bool, unplayable_notes: bool) -> i32 {
+    let mut code = 0;
+    if invalid_notes {
+        code += EXIT_INVALID_NOTES;
+    }
+    if unplayable_notes {
+        code += EXIT_UNPLAYABLE_NOTES;
+    }
+    code
+}
+
+/// the number of notes in a transposed tab that aren't on the output harp
+pub(crate) fn count_unplayable_notes(tab: &str) -> usize {
+    tab.split_whitespace()
+        .filter(|token| token.contains('X'))
+        .count()
+}
+
 pub struct RunOptions<'a> {
+    /// `-` reads the tab from stdin
     pub filename: &'a str,
+    /// write the result to this file instead of stdout, can be `filename` to change it in place
+    pub output_file: Option<&'a str>,
     pub semitones: i32,
-    pub from_position: i32,
+    /// taken from the header of `.harptab` files if `None`, defaults to 1
+    pub from_position: Option<i32>,
     pub to_position: Option<i32>,
     pub octave_shift: i32,
     pub keep_errors: bool,
-    pub style: Style,
-    pub input_tuning: &'a str,
+    /// taken from the header of `.harptab` files if `None`
+    pub style: Option<Style>,
+    /// taken from the header of `.harptab` files if `None`, defaults to richter
+    pub input_tuning: Option<&'a str>,
     pub output_tuning: &'a str,
     pub _play_audio: bool,
     pub playable_positions: bool,
-    pub allow_bends: bool,
+    /// search the playable positions on every tuning instead of `output_tuning`
+    pub all_tunings: bool,
+    /// list the techniques every playable position needs
+    pub verbose: bool,
+    /// choose an octave per line of the tab to keep it playable in `to_position`
+    pub split_octaves: bool,
+    /// techniques the playable positions and octave split may use
+    pub technique: TechniqueProfile,
+    /// only keep playable positions in this range, or pick the octave that fits it
+    pub hole_range: Option<HoleRange>,
+    pub loop_playback: bool,
+    pub from_note: Option<usize>,
+    pub to_note: Option<usize>,
+    pub practice: PracticeOptions,
+    /// taken from the header of `.harptab` files if `None`, defaults to C
+    pub key: Option<&'a str>,
+    /// tempo of the backing track, metronome and practice loop,
+    /// taken from the header of `.harptab` files if `None`
+    pub bpm: Option<f64>,
+    /// print the result as a `.harptab` file, always done for `.harptab` input
+    pub harptab_output: bool,
+    pub backing: Option<BackingOptions>,
+    pub backing_with_tab: bool,
+    pub wav_file: Option<&'a str>,
+    pub metronome: Option<Metronome>,
+    pub temperament: Temperament,
+    /// frequency of A4 in Hz
+    pub reference_pitch: f32,
+    pub reed_offsets_file: Option<&'a str>,
+    /// treat the file as a wav recording of a harp in this key, and transcribe it
+    pub transcribe_key: Option<&'a str>,
+    /// wav recording of the (transposed) tab played on a harp in `key`, to check the intonation of
+    pub check_file: Option<&'a str>,
+    /// print the result as text or as json
+    pub format: OutputFormat,
+    /// write the transposed notes as tab, note names in `key`, degrees in the target position or all three
+    pub notation: Notation,
+    /// what the tab is written in, note names are on a harp in `key` and degrees in `from_position`
+    pub input_format: InputFormat,
 }
 
 fn get_index_a440(note: &str, notes: &[String]) -> Option<i32> {
@@ -87,9 +276,11 @@ fn get_index_a440(note: &str, notes: &[String]) -> Option<i32> {
     }
 }
 
-pub fn run(options: RunOptions) {
+/// transpose (and play) a tab, returning the exit code
+pub fn run(options: RunOptions) -> i32 {
     let RunOptions {
         filename,
+        output_file,
         mut semitones,
         from_position,
         to_position,
@@ -100,16 +291,97 @@ pub fn run(options: RunOptions) {
         output_tuning,
         _play_audio,
         playable_positions,
-        allow_bends,
+        all_tunings,
+        verbose,
+        split_octaves: split_by_phrase,
+        technique,
+        hole_range,
+        loop_playback,
+        from_note,
+        to_note,
+        mut practice,
+        key,
+        bpm,
+        harptab_output,
+        mut backing,
+        backing_with_tab,
+        wav_file,
+        mut metronome,
+        temperament,
+        reference_pitch,
+        reed_offsets_file,
+        transcribe_key,
+        check_file,
+        format,
+        notation,
+        input_format,
     } = options;
 
-    let tab = match fs::read_to_string(filename) {
-        Ok(s) => s,
-        Err(_) => {
-            eprintln!("could not read file");
+    let is_harptab = filename.ends_with(".harptab");
+    let tab = if let Some(transcribe_key) = transcribe_key {
+        let (samples, sample_rate) = read_wav(filename).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(-1);
+        });
+        let transcription = transcribe(
+            &samples,
+            sample_rate,
+            input_tuning.unwrap_or("richter"),
+            transcribe_key,
+            style.unwrap_or(Style::Default),
+        );
+        eprint!("{}", transcription.report());
+        transcription.tab()
+    } else {
+        let text = if filename == "-" {
+            std::io::read_to_string(std::io::stdin())
+        } else {
+            fs::read_to_string(filename)
+        };
+        match text {
+            Ok(s) => s,
+            Err(_) => {
+                eprintln!("could not read file");
+                std::process::exit(-1);
+            }
+        }
+    };
+
+    let document = if is_harptab {
+        HarpTab::from_str(&tab).unwrap_or_else(|e| {
+            eprintln!("invalid harptab file: {}", e);
             std::process::exit(-1);
+        })
+    } else {
+        HarpTab {
+            header: TabHeader::default(),
+            body: tab,
         }
     };
+    let header = &document.header;
+    let from_position = from_position.unwrap_or(header.position.map_or(1, |p| p as i32));
+    let style = style.or(header.style).unwrap_or(Style::Default);
+    let input_tuning = input_tuning.unwrap_or(header.tuning.as_deref().unwrap_or("richter"));
+    let key = key.unwrap_or(header.key.as_deref().unwrap_or("C"));
+    let bpm = bpm.or(header.tempo);
+    let note_duration = bpm.map_or(NOTE_DURATION, |bpm| 60.0 / bpm);
+    if let Some(bpm) = bpm {
+        practice.start_bpm = bpm;
+        if let Some(backing) = backing.as_mut() {
+            backing.bpm = bpm;
+        }
+        if let Some(metronome) = metronome.as_mut() {
+            metronome.bpm = bpm;
+        }
+    }
+    let (tab, input_errors) = input_to_tab(
+        &document.body,
+        input_format,
+        input_tuning,
+        style,
+        key,
+        from_position as u32,
+    );
 
     if let Some(to_position) = to_position {
         semitones = positions_to_semitones(from_position, to_position, octave_shift);
@@ -117,28 +389,384 @@ pub fn run(options: RunOptions) {
         semitones = positions_to_semitones(from_position, 1, octave_shift);
     }
 
-    let res = if playable_positions {
-        transpose_playable_positions(
+    if let (Some(range), false) = (&hole_range, playable_positions) {
+        semitones = fit_octave(&tab, semitones, range, style, input_tuning, output_tuning)
+            .unwrap_or_else(|| {
+                eprintln!("the tab doesn't fit the hole range in any octave");
+                std::process::exit(-1);
+            });
+    }
+
+    // tokens that aren't notes, whatever the tab is transposed to
+    let (_, errors) = transpose_body(
+        &tab,
+        0,
+        from_position as u32,
+        false,
+        style,
+        input_tuning,
+        input_tuning,
+    );
+    let invalid_notes = !errors.is_empty() || !input_errors.is_empty();
+    let mut status = exit_code(invalid_notes, false);
+    // the json of results that aren't a transposed tab
+    let mut report = None;
+    let res = if let (true, true) = (playable_positions, all_tunings) {
+        let playable = get_playable_positions_in_all_tunings(
+            &tab,
+            from_position as u32,
+            input_tuning,
+            style,
+            &technique,
+            hole_range.as_ref(),
+        );
+        if format == OutputFormat::Json {
+            report = Some(serde_json::json!(playable));
+        }
+        format_tuning_positions(&playable)
+    } else if playable_positions {
+        let playable = playable_positions_in_range(
             &tab,
             from_position as u32,
             input_tuning,
             output_tuning,
             style,
-            allow_bends,
+            &technique,
+            hole_range.as_ref(),
+        );
+        if format == OutputFormat::Json {
+            let reports: Vec<PositionReport> = playable
+                .iter()
+                .map(|&(position, semitones)| {
+                    PositionReport::new(
+                        &tab,
+                        position,
+                        semitones,
+                        style,
+                        input_tuning,
+                        output_tuning,
+                    )
+                })
+                .collect();
+            report = Some(serde_json::json!(reports));
+        }
+        transpose_playable_positions(
+            &tab,
+            &playable,
+            input_tuning,
+            output_tuning,
+            style,
+            verbose,
+            notation,
+            key,
         )
+    } else if let (true, Some(to_position)) = (split_by_phrase, to_position) {
+        let split = split_octaves(
+            &tab,
+            from_position as u32,
+            to_position as u32,
+            input_tuning,
+            output_tuning,
+            style,
+            &technique,
+        );
+        match split {
+            Ok(split) => {
+                let tab = split.tab(&tab);
+                status = exit_code(invalid_notes, count_unplayable_notes(&tab) > 0);
+                if format == OutputFormat::Json {
+                    report = Some(serde_json::json!({
+                        "position": to_position,
+                        "semitones": split.semitones,
+                        "phrases": split.phrases,
+                        "tab": tab,
+                    }));
+                }
+                tab
+            }
+            Err(lines) => {
+                let lines: Vec<String> = lines.iter().map(|l| l.to_string()).collect();
+                eprintln!(
+                    "line {} can't be played in {} position in any octave",
+                    lines.join(", "),
+                    to_ordinal(to_position as u32)
+                );
+                std::process::exit(-1);
+            }
+        }
     } else {
-        let (tabs, _) =
-            transpose_tabs(tab, semitones, no_error, style, input_tuning, output_tuning);
+        let (tabs, _) = transpose_body(
+            &tab,
+            semitones,
+            from_position as u32,
+            no_error,
+            style,
+            input_tuning,
+            output_tuning,
+        );
+        status = exit_code(invalid_notes, count_unplayable_notes(&tabs) > 0);
         tabs
     };
-    print!("{}", res);
+    // only tabs transposed to a single position have one
+    let output_position = || match to_position {
+        Some(position) => position as u32,
+        None => semitones_to_position(from_position as u32, semitones),
+    };
+    let body = if playable_positions {
+        res.clone()
+    } else {
+        render_notation(&res, notation, output_tuning, style, key, output_position())
+    };
+    let output = if (harptab_output || is_harptab) && !playable_positions {
+        let output = HarpTab {
+            header: TabHeader {
+                key: Some(key.to_string()),
+                position: Some(output_position()),
+                tuning: Some(output_tuning.to_string()),
+                style: Some(style),
+                ..header.clone()
+            },
+            body,
+        };
+        output.to_string()
+    } else {
+        body
+    };
+    let check = check_file.map(|check_file| {
+        let (samples, sample_rate) = read_wav(check_file).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(-1);
+        });
+        let transcription = transcribe(&samples, sample_rate, output_tuning, key, style);
+        check_performance(&transcription, &res, output_tuning, style, key)
+    });
+    let output = match format {
+        OutputFormat::Text => output,
+        OutputFormat::Json => {
+            let report = report.unwrap_or_else(|| {
+                serde_json::json!(TranspositionReport::new(
+                    &tab,
+                    output,
+                    semitones,
+                    from_position as u32,
+                    style,
+                    input_tuning,
+                    output_tuning,
+                ))
+            });
+            match &check {
+                Some(check) => to_json(&serde_json::json!({
+                    "transposition": report,
+                    "check": check,
+                })),
+                None => to_json(&report),
+            }
+        }
+    };
+    match output_file {
+        Some(output_file) => {
+            if let Err(e) = fs::write(output_file, output) {
+                eprintln!("could not write file: {}", e);
+                std::process::exit(-1);
+            }
+        }
+        None => print!("{}", output),
+    }
+
+    if let (OutputFormat::Text, Some(check)) = (format, check) {
+        print!("\n{}", check.report());
+    }
+
+    if wav_file.is_none() && !_play_audio {
+        return status;
+    }
+
+    let reed_offsets = match reed_offsets_file.map(fs::read_to_string) {
+        None => ReedOffsets::default(),
+        Some(Ok(text)) => ReedOffsets::parse(&text).unwrap_or_else(|e| {
+            eprintln!("invalid reed offsets: {}", e);
+            std::process::exit(-1);
+        }),
+        Some(Err(_)) => {
+            eprintln!("could not read reed offsets file");
+            std::process::exit(-1);
+        }
+    };
+    let intonation = Intonation::new(
+        temperament,
+        reference_pitch,
+        key,
+        output_tuning,
+        &reed_offsets,
+    );
+
+    let notes = get_playback_notes_in_key(&res, output_tuning, style, key);
+    let rendered = backing.map(|backing| {
+        let melody = if backing_with_tab { &notes[..] } else { &[] };
+        render_backing_track(
+            &backing,
+            key,
+            output_position(),
+            melody,
+            metronome.as_ref(),
+            &intonation,
+        )
+    });
+
+    if let Some(wav_file) = wav_file {
+        let samples = match (&rendered, &metronome) {
+            (Some(samples), _) => samples.clone(),
+            (None, Some(metronome)) => {
+                let mut voices = melody_voices(&notes, metronome.beat_duration());
+                voices.extend(metronome.click_voices(notes.len()));
+                render_with_intonation(&voices, &intonation)
+            }
+            (None, None) => {
+                render_with_intonation(&melody_voices(&notes, note_duration), &intonation)
+            }
+        };
+        if let Err(e) = write_wav(wav_file, &samples) {
+            eprintln!("could not write wav file: {}", e);
+            std::process::exit(-1);
+        }
+    }
 
     #[cfg(not(target_arch = "wasm32"))]
     if _play_audio {
-        let (_stream, stream_handle) = OutputStream::try_default().unwrap();
-        let sink = Sink::try_new(&stream_handle).unwrap();
-        play_tab(res, output_tuning, style, &sink);
-        sink.sleep_until_end();
+        if let Some(samples) = rendered {
+            let mut output = RodioOutput::new();
+            output.play_samples(&samples);
+            output.sleep_until_end();
+        } else {
+            let start = from_note.map_or(0, |n| n.saturating_sub(1));
+            let end = to_note.unwrap_or(notes.len());
+            play_section(
+                notes,
+                start..end,
+                loop_playback.then_some(practice),
+                metronome,
+                note_duration,
+                intonation,
+            );
+        }
+    }
+    status
+}
+
+/// ask quiz questions on the command line, playing them through the default audio device
+#[cfg(not(target_arch = "wasm32"))]
+pub fn run_quiz(options: QuizOptions, questions: u32, seed: u64) {
+    use std::io::{BufRead, Write};
+
+    let what = match options.answer {
+        AnswerKind::Hole => "hole",
+        AnswerKind::ScaleDegree => "scale degree",
+        AnswerKind::Note => "note",
+    };
+    eprintln!("seed {}", seed);
+    let mut quiz = QuizSession::new(options, seed);
+    let mut player = Player::new(RodioOutput::new());
+    let mut lines = std::io::stdin().lock().lines();
+
+    'questions: for n in 1..=questions {
+        let Some(question) = quiz.next_question().cloned() else {
+            eprintln!("no notes to ask for");
+            return;
+        };
+        let notes = question
+            .indices
+            .iter()
+            .map(|&index| PlaybackNote {
+                index,
+                chord: Vec::new(),
+                reeds: Vec::new(),
+                span: 0..0,
+            })
+            .collect();
+        player.set_notes(notes);
+
+        loop {
+            player.play();
+            while player.is_playing() {
+                player.update();
+                std::thread::sleep(std::time::Duration::from_millis(5));
+            }
+
+            if let Some(given) = &question.given {
+                print!(
+                    "{}/{}: starting on {}, name the second {} (r to replay): ",
+                    n, questions, given, what
+                );
+            } else {
+                print!("{}/{}: name the {} (r to replay): ", n, questions, what);
+            }
+            std::io::stdout().flush().unwrap();
+
+            let Some(Ok(line)) = lines.next() else {
+                println!();
+                break 'questions;
+            };
+            if line.trim() == "r" {
+                continue;
+            }
+            if quiz.answer(&line) {
+                println!("correct");
+            } else {
+                println!("wrong, it was {}", question.answer);
+            }
+            break;
+        }
+    }
+
+    let score = quiz.score();
+    println!("score: {}/{}", score.correct, score.total);
+    for (hole, score) in quiz.per_hole() {
+        println!("{:>5} {}/{}", hole, score.correct, score.total);
+    }
+}
+
+/// play part of a tab with notes of `note_duration` seconds, looping it with increasing tempo if practice options are given
+#[cfg(not(target_arch = "wasm32"))]
+fn play_section(
+    notes: Vec<PlaybackNote>,
+    section: Range<usize>,
+    practice: Option<PracticeOptions>,
+    metronome: Option<Metronome>,
+    note_duration: f64,
+    intonation: Intonation,
+) {
+    let mut output = RodioOutput::new();
+    output.set_intonation(intonation);
+    let mut player = Player::new(output);
+    player.set_notes(notes);
+    player.set_section(Some(section));
+    // the metronome sets its own beat
+    player.set_note_duration(note_duration);
+    player.set_metronome(metronome);
+
+    let mut session = practice.map(PracticeSession::new);
+    if let Some(session) = &session {
+        player.set_looping(true);
+        player.set_count_in(session.options().count_in);
+        player.set_note_duration(session.note_duration());
+        eprintln!("{} bpm", session.bpm());
+    }
+
+    player.play();
+    while player.is_playing() {
+        for event in player.update() {
+            if let (PlaybackEvent::LoopEnd, Some(session)) = (event, session.as_mut()) {
+                // there is no way to report mistakes here, so every loop counts as clean
+                if session.loop_finished(true) {
+                    player.set_note_duration(session.note_duration());
+                    eprintln!("{} bpm", session.bpm());
+                }
+                if session.finished() {
+                    player.stop();
+                }
+            }
+        }
+        std::thread::sleep(std::time::Duration::from_millis(5));
     }
 }
 
@@ -190,6 +818,11 @@ fn convert_to_harpsurgery_style(note: &str) -> String {
 
 /// change the tab style of a single note
 pub fn change_tab_style_single(note: &str, style: Style) -> String {
+    if let Some(ornamented) = OrnamentedNote::split(note) {
+        return ornamented
+            .map(|n| change_tab_style_single(n, style))
+            .format();
+    }
     match style {
         Style::BBends => note.replace('\'', "b"),
         Style::Harpsurgery => convert_to_harpsurgery_style(note),
@@ -271,7 +904,182 @@ pub fn tab_to_note(
     }
 }
 
+/// every token of a tab and what `f` makes of it, given the notes and duplicated notes
+/// of the tuning in the tab's style. tokens `f` gives `X` for have no output
+fn map_tab_tokens(
+    tab: &str,
+    tuning: &str,
+    style: Style,
+    f: impl Fn(&str, &[String], &[String]) -> &'static str,
+) -> Vec<Vec<TransposedToken>> {
+    let (notes, duplicated_notes) = tuning_to_notes_in_order(tuning);
+    let notes = change_tab_style(&notes, style);
+    let duplicated_notes = change_tab_style(&duplicated_notes, style);
+    let mut offset = 0;
+    tab.split_inclusive('\n')
+        .enumerate()
+        .map(|(i, line)| {
+            let start = offset;
+            offset += line.len();
+            let line = line.trim_end_matches('\n').trim_end_matches('\r');
+            hole_group::tokens(line)
+                .into_iter()
+                .map(|(span, token)| {
+                    let output = f(token, &notes, &duplicated_notes);
+                    TransposedToken {
+                        output: (output != "X").then(|| output.to_string()),
+                        input: token.to_string(),
+                        line: i + 1,
+                        span: start + span.start..start + span.end,
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// replace every note of a tab by what `f` makes of it, keeping the lines of the tab
+fn map_tab_notes(
+    tab: &str,
+    tuning: &str,
+    style: Style,
+    f: impl Fn(&str, &[String], &[String]) -> &'static str,
+) -> String {
+    map_tab_tokens(tab, tuning, style, f)
+        .iter()
+        .map(|line| {
+            let mapped: Vec<&str> = line
+                .iter()
+                .map(|token| token.output.as_deref().unwrap_or("X"))
+                .collect();
+            format!("{}\n", mapped.join(" "))
+        })
+        .collect()
+}
+
+/// the note names of a tab on a harp in `key`
+pub fn tab_to_note_names(tab: &str, tuning: &str, style: Style, key: &str) -> String {
+    map_tab_notes(tab, tuning, style, |note, notes, duplicated_notes| {
+        tab_to_note(note, key, notes, duplicated_notes)
+    })
+}
+
+/// every token of a tab with its note name on a harp in `key`
+pub fn note_name_tokens(tab: &str, tuning: &str, style: Style, key: &str) -> Vec<TransposedToken> {
+    let lines = map_tab_tokens(tab, tuning, style, |note, notes, duplicated_notes| {
+        tab_to_note(note, key, notes, duplicated_notes)
+    });
+    lines.into_iter().flatten().collect()
+}
+
+/// the scale degrees of a tab played in `position`
+pub fn tab_to_scale_degrees(tab: &str, tuning: &str, style: Style, position: u32) -> String {
+    map_tab_notes(tab, tuning, style, |note, notes, duplicated_notes| {
+        tab_to_scale_degree(note, position, notes, duplicated_notes)
+    })
+}
+
+/// every token of a tab with its scale degree in `position`
+pub fn scale_degree_tokens(
+    tab: &str,
+    tuning: &str,
+    style: Style,
+    position: u32,
+) -> Vec<TransposedToken> {
+    let lines = map_tab_tokens(tab, tuning, style, |note, notes, duplicated_notes| {
+        tab_to_scale_degree(note, position, notes, duplicated_notes)
+    });
+    lines.into_iter().flatten().collect()
+}
+
+/// how the notes of a tab are written
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Notation {
+    #[default]
+    Tab,
+    /// note names on a harp in a key
+    NoteNames,
+    /// scale degrees in a position
+    Degrees,
+    /// tab, note names and degrees stacked
+    All,
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+#[error("`{0}` is not a valid notation (expected tab, notes, degrees or all)")]
+pub struct NotationError(String);
+
+impl FromStr for Notation {
+    type Err = NotationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "tab" => Ok(Notation::Tab),
+            "notes" => Ok(Notation::NoteNames),
+            "degrees" => Ok(Notation::Degrees),
+            "all" => Ok(Notation::All),
+            _ => Err(NotationError(s.to_string())),
+        }
+    }
+}
+
+/// write a tab in a notation, with note names on a harp in `key` and degrees in `position`.
+/// `All` puts the tab, note names and degrees of every line below each other, aligned by note,
+/// followed by an empty line
+pub fn render_notation(
+    tab: &str,
+    notation: Notation,
+    tuning: &str,
+    style: Style,
+    key: &str,
+    position: u32,
+) -> String {
+    match notation {
+        Notation::Tab => tab.to_string(),
+        Notation::NoteNames => tab_to_note_names(tab, tuning, style, key),
+        Notation::Degrees => tab_to_scale_degrees(tab, tuning, style, position),
+        Notation::All => {
+            let names = map_tab_tokens(tab, tuning, style, |note, notes, duplicated_notes| {
+                tab_to_note(note, key, notes, duplicated_notes)
+            });
+            let degrees = map_tab_tokens(tab, tuning, style, |note, notes, duplicated_notes| {
+                tab_to_scale_degree(note, position, notes, duplicated_notes)
+            });
+            let mut res = String::new();
+            for (names, degrees) in names.iter().zip(degrees.iter()) {
+                if names.is_empty() {
+                    res.push('\n');
+                    continue;
+                }
+                let mut rows = [String::new(), String::new(), String::new()];
+                for (name, degree) in names.iter().zip(degrees.iter()) {
+                    let cells = [
+                        name.input.as_str(),
+                        name.output.as_deref().unwrap_or("X"),
+                        degree.output.as_deref().unwrap_or("X"),
+                    ];
+                    let width = cells.iter().map(|c| c.chars().count()).max().unwrap_or(0);
+                    for (row, cell) in rows.iter_mut().zip(cells) {
+                        row.push_str(&format!("{:width$} ", cell, width = width));
+                    }
+                }
+                for row in rows {
+                    res.push_str(row.trim_end());
+                    res.push('\n');
+                }
+                res.push('\n');
+            }
+            res
+        }
+    }
+}
+
 /// given the name of a tuning, return 1. a vec of every note in that tuning and 2. a vec of duplicated notes and their alternatives
+/// number of holes of a tuning
+pub fn hole_count(tuning: &str) -> u32 {
+    harptool::Tuning::from(tuning_to_notes(tuning)).blow.len() as u32
+}
+
 pub fn tuning_to_notes_in_order(tuning: &str) -> (Vec<String>, Vec<String>) {
     let notes = tuning_to_notes(tuning);
     harptool::str_to_notes_in_order(notes)
@@ -288,7 +1096,15 @@ pub fn play_tab(tab: String, tuning: &str, style: Style, sink: &rodio::Sink) {
 #[cfg(not(target_arch = "wasm32"))]
 pub fn play_tab_in_key(tab: String, tuning: &str, style: Style, key: &str, sink: &rodio::Sink) {
     let mut indices = get_audio_indices(tab, tuning, style);
+    let offset = key_to_index_offset(key);
+    for i in indices.iter_mut() {
+        *i += offset;
+    }
+    play_indices_as_audio(&indices, sink);
+}
 
+/// semitone offset of a harp key relative to a C harp
+fn key_to_index_offset(key: &str) -> i32 {
     let sharp = if key == "F#" {
         Some(true)
     } else if ["Bb", "Eb", "Ab", "Db"].contains(&key) {
@@ -303,10 +1119,7 @@ pub fn play_tab_in_key(tab: String, tuning: &str, style: Style, key: &str, sink:
     if ["G", "A", "B", "Ab", "Bb"].contains(&key) {
         offset -= 12;
     }
-    for i in indices.iter_mut() {
-        *i += offset;
-    }
-    play_indices_as_audio(&indices, sink);
+    offset
 }
 
 /// given indices (0 being A440), play them as audio
@@ -317,22 +1130,109 @@ pub fn play_indices_as_audio(indices: &[i32], sink: &rodio::Sink) {
     }
 }
 
+/// given indices (0 being A440), play them as audio with metronome clicks, one note per beat
+#[cfg(not(target_arch = "wasm32"))]
+pub fn play_indices_with_metronome(indices: &[i32], metronome: &Metronome, sink: &rodio::Sink) {
+    let notes: Vec<PlaybackNote> = indices
+        .iter()
+        .map(|index| PlaybackNote {
+            index: *index,
+            chord: Vec::new(),
+            reeds: Vec::new(),
+            span: 0..0,
+        })
+        .collect();
+    let mut voices = melody_voices(&notes, metronome.beat_duration());
+    voices.extend(metronome.click_voices(notes.len()));
+    let samples = render(&voices);
+    sink.append(rodio::buffer::SamplesBuffer::new(1, SAMPLE_RATE, samples));
+}
+
 /// return a vec of note indices for the tab (with 0 = A440)
 pub fn get_audio_indices(tab: String, tuning: &str, style: Style) -> Vec<i32> {
-    let (notes, duplicated_notes) = tuning_to_notes_in_order(tuning);
-    let notes = change_tab_style(&notes, style);
-    let duplicated_notes = change_tab_style(&duplicated_notes, style);
+    get_playback_notes(&tab, tuning, style)
+        .into_iter()
+        .map(|note| note.index)
+        .collect()
+}
+
+/// return the playable notes of a tab along with their position in the tab (with 0 = A440)
+pub fn get_playback_notes(tab: &str, tuning: &str, style: Style) -> Vec<PlaybackNote> {
+    let table = TuningTable::get(tuning, style);
+    let (notes, duplicated_notes) = (&table.notes, &table.duplicated_notes);
+    // looked up before fixing enharmonics, which would move 3 blow onto the 2 draw reed
+    let reed = |note: &str| {
+        let default = match notes.iter().position(|x| x == note) {
+            Some(p) => &table.default_notes[p],
+            None => {
+                let p = duplicated_notes.iter().position(|x| x == note)?;
+                &table.default_duplicated_notes[p]
+            }
+        };
+        Reed::of(default)
+    };
+    let note = |hole: &str| {
+        let index = get_index_a440(fix_enharmonics(hole, duplicated_notes), notes)?;
+        Some((index, reed(hole)?))
+    };
 
-    tab.lines()
-        .flat_map(|line| {
-            line.split_whitespace().filter_map(|note| {
-                let note = fix_enharmonics(note, &duplicated_notes);
-                get_index_a440(note, &notes)
+    hole_group::tokens(tab)
+        .into_iter()
+        .filter_map(|(span, token)| {
+            if let Some((index, reed)) = note(token) {
+                return Some(PlaybackNote {
+                    index,
+                    chord: Vec::new(),
+                    reeds: vec![reed],
+                    span,
+                });
+            }
+
+            // ornamented notes play their main note
+            if let Some(ornamented) = OrnamentedNote::parse(token, notes, duplicated_notes) {
+                return note(&ornamented.note).map(|(index, reed)| PlaybackNote {
+                    index,
+                    chord: Vec::new(),
+                    reeds: vec![reed],
+                    span,
+                });
+            }
+
+            // groups of holes play as chords
+            let group = HoleGroup::parse(token, notes, duplicated_notes)?;
+            let (mut indices, reeds): (Vec<i32>, Vec<Reed>) =
+                group.holes.iter().filter_map(|hole| note(hole)).unzip();
+            if indices.is_empty() {
+                return None;
+            }
+            Some(PlaybackNote {
+                index: indices.remove(0),
+                chord: indices,
+                reeds,
+                span,
             })
         })
         .collect()
 }
 
+/// return the playable notes of a tab in a certain key along with their position in the tab
+pub fn get_playback_notes_in_key(
+    tab: &str,
+    tuning: &str,
+    style: Style,
+    key: &str,
+) -> Vec<PlaybackNote> {
+    let offset = key_to_index_offset(key);
+    let mut notes = get_playback_notes(tab, tuning, style);
+    for note in notes.iter_mut() {
+        note.index += offset;
+        for index in note.chord.iter_mut() {
+            *index += offset;
+        }
+    }
+    notes
+}
+
 /// transpose an entire tab
 pub fn transpose_tabs(
     tab: String,
@@ -342,57 +1242,14 @@ pub fn transpose_tabs(
     input_tuning: &str,
     output_tuning: &str,
 ) -> (String, Vec<String>) {
-    let (input_notes, duplicated_notes) = tuning_to_notes_in_order(input_tuning);
-    let input_notes = change_tab_style(&input_notes, style);
-    let duplicated_notes = change_tab_style(&duplicated_notes, style);
-
-    let (output_notes, _) = tuning_to_notes_in_order(output_tuning);
-    let output_notes = change_tab_style(&output_notes, style);
-
-    let mut errors: Vec<String> = Vec::new();
-
-    // replace double quotes in input with two single quotes
-    let tab = tab.replace('"', "''");
-
-    let result = tab
-        .lines()
-        .map(|line| {
-            line.split_whitespace()
-                .map(|note| fix_enharmonics(note, &duplicated_notes))
-                .map(|note| transpose(&input_notes, &output_notes, note, semitones))
-                .flat_map(|transpose_result| match transpose_result {
-                    Ok(new_note) => [new_note, " "],
-                    Err(TransposeError::InvalidNote(input_note)) => {
-                        errors.push(input_note.to_string());
-                        if keep_errors {
-                            [input_note, " "]
-                        } else {
-                            ["", ""]
-                        }
-                    }
-                })
-                .collect::<String>()
-        })
-        .chain(["".to_owned()])
-        .collect::<Vec<String>>()
-        .join("\n");
-
-    (result, errors)
+    Transposer::new(&tab, style, input_tuning, output_tuning).transpose(semitones, keep_errors)
 }
 
 trait Tab {
-    fn contains_overblow(&self) -> bool;
-    fn contains_bend(&self) -> bool;
     fn is_playable(&self) -> bool;
 }
 
 impl Tab for str {
-    fn contains_overblow(&self) -> bool {
-        self.contains('o') || self.contains('#')
-    }
-    fn contains_bend(&self) -> bool {
-        self.contains('\'') || self.contains('b')
-    }
     fn is_playable(&self) -> bool {
         !self.contains('X')
     }
@@ -407,30 +1264,79 @@ pub fn get_playable_positions(
     style: Style,
     allow_bends: bool,
 ) -> Vec<(u32, i32)> {
-    let mut results: Vec<(u32, i32)> = Vec::new();
-    if tab.is_empty() {
-        return results;
-    }
-    for semitones in -24..=24 {
-        let (notes, _errors) = transpose_tabs(
-            tab.to_string(),
-            semitones,
-            false,
-            style,
-            input_tuning,
-            output_tuning,
-        );
-        let is_playable = if allow_bends {
-            notes.is_playable() && !notes.contains_overblow()
-        } else {
-            notes.is_playable() && !notes.contains_overblow() && !notes.contains_bend()
-        };
+    let technique = if allow_bends {
+        TechniqueProfile::bends()
+    } else {
+        TechniqueProfile::none()
+    };
+    get_playable_positions_with_technique(
+        tab,
+        input_position,
+        input_tuning,
+        output_tuning,
+        style,
+        &technique,
+    )
+}
+
+/// find all positions a tab is playable in with the techniques of a profile and their semitone offsets
+pub fn get_playable_positions_with_technique(
+    tab: &str,
+    input_position: u32,
+    input_tuning: &str,
+    output_tuning: &str,
+    style: Style,
+    technique: &TechniqueProfile,
+) -> Vec<(u32, i32)> {
+    Transposer::new(tab, style, input_tuning, output_tuning)
+        .playable_positions(input_position, technique)
+}
+
+/// a tuning and position a tab is playable in
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TuningPosition {
+    pub tuning: &'static str,
+    pub position: u32,
+    pub semitones: i32,
+    /// see `tab_difficulty`
+    pub difficulty: u32,
+}
 
-        if is_playable {
-            let position = semitones_to_position(input_position, semitones);
-            results.push((position, semitones));
+/// names of all known tunings, sorted
+pub fn get_tunings() -> Vec<&'static str> {
+    let mut tunings: Vec<&'static str> = harptool::tunings::get_tunings().keys().copied().collect();
+    tunings.sort_unstable();
+    tunings
+}
+
+/// find the positions a tab is playable in on every tuning, easiest first
+pub fn get_playable_positions_in_all_tunings(
+    tab: &str,
+    input_position: u32,
+    input_tuning: &str,
+    style: Style,
+    technique: &TechniqueProfile,
+    hole_range: Option<&HoleRange>,
+) -> Vec<TuningPosition> {
+    let mut results = Vec::new();
+    for tuning in get_tunings() {
+        let transposer = Transposer::new(tab, style, input_tuning, tuning);
+        let mut playable = transposer.playable_positions(input_position, technique);
+        if let Some(range) = hole_range {
+            playable =
+                filter_positions_by_range(&playable, tab, range, style, input_tuning, tuning);
+        }
+        for (position, semitones) in playable {
+            let (transposed, _) = transposer.transpose(semitones, false);
+            results.push(TuningPosition {
+                tuning,
+                position,
+                semitones,
+                difficulty: tab_difficulty(&transposed, tuning, style),
+            });
         }
     }
+    results.sort_by_key(|p| (p.difficulty, p.semitones.abs(), p.tuning));
     results
 }
 
@@ -447,23 +1353,61 @@ pub fn to_ordinal(num: u32) -> String {
     res
 }
 
-fn transpose_playable_positions(
+fn format_tuning_positions(playable: &[TuningPosition]) -> String {
+    let mut res = format!(
+        "{:24} {:9} {:>9} {:>10}\n",
+        "tuning", "position", "semitones", "difficulty"
+    );
+    for p in playable {
+        res.push_str(&format!(
+            "{:24} {:9} {:>+9} {:>10}\n",
+            p.tuning,
+            to_ordinal(p.position),
+            p.semitones,
+            p.difficulty
+        ));
+    }
+    res
+}
+
+/// the playable positions of a tab, only keeping the ones in `hole_range`
+fn playable_positions_in_range(
     tab: &str,
     input_position: u32,
     input_tuning: &str,
     output_tuning: &str,
     style: Style,
-    allow_bends: bool,
-) -> String {
-    let playable = get_playable_positions(
+    technique: &TechniqueProfile,
+    hole_range: Option<&HoleRange>,
+) -> Vec<(u32, i32)> {
+    let playable = get_playable_positions_with_technique(
         tab,
         input_position,
         input_tuning,
         output_tuning,
         style,
-        allow_bends,
+        technique,
     );
+    match hole_range {
+        Some(range) => {
+            filter_positions_by_range(&playable, tab, range, style, input_tuning, output_tuning)
+        }
+        None => playable,
+    }
+}
 
+#[allow(clippy::too_many_arguments)]
+fn transpose_playable_positions(
+    tab: &str,
+    playable: &[(u32, i32)],
+    input_tuning: &str,
+    output_tuning: &str,
+    style: Style,
+    verbose: bool,
+    notation: Notation,
+    key: &str,
+) -> String {
+    let transposer = Transposer::new(tab, style, input_tuning, output_tuning);
     let mut res = String::from("");
     for (position, semitones) in playable.iter() {
         res.push_str(
@@ -475,15 +1419,21 @@ fn transpose_playable_positions(
             )
             .as_str(),
         );
-        let (transposed, _) = transpose_tabs(
-            tab.to_string(),
-            *semitones,
-            true,
-            style,
-            input_tuning,
+        let (transposed, _) = transposer.transpose(*semitones, true);
+        if verbose {
+            let inventory = TechniqueInventory::new(&transposed, output_tuning, style);
+            for line in inventory.report().lines() {
+                res.push_str(&format!("  {}\n", line));
+            }
+        }
+        res.push_str(&render_notation(
+            &transposed,
+            notation,
             output_tuning,
-        );
-        res.push_str(&transposed);
+            style,
+            key,
+            *position,
+        ));
         res.push('\n');
     }
     res
@@ -636,6 +1586,8 @@ mod tests {
 
         let res = get_tabkeyboard_layout("asdf");
         assert_eq!(res, expected);
+        assert_eq!(hole_count("richter"), 10);
+        assert_eq!(hole_count("5 hole richter"), 5);
 
         let res = get_tabkeyboard_layout("wilde tuning");
         let expected = vec![
@@ -877,6 +1829,150 @@ mod tests {
         let res = get_playable_positions(tab, 1, "richter", "richter", Style::Default, true);
         assert_eq!(res, expected);
     }
+
+    #[test]
+    fn test_tab_to_note_names() {
+        let res = tab_to_note_names("4 -4 5\n-2 -3'", "richter", Style::Default, "A");
+        assert_eq!(res, "A B Db\nE G\n");
+        let res = tab_to_scale_degrees("-2 -3 4 asdf", "richter", Style::Default, 2);
+        assert_eq!(res, "1 3 4 X\n");
+
+        let tokens = scale_degree_tokens("-2 -3\n4 asdf", "richter", Style::Default, 2);
+        assert_eq!(tokens[2].output.as_deref(), Some("4"));
+        assert_eq!((tokens[2].line, tokens[2].span.clone()), (2, 6..7));
+        assert_eq!(tokens[3].output, None);
+    }
+
+    #[test]
+    fn test_render_notation() {
+        let tab = "-2 -3' 4\n\n-4 (4 5)";
+        let res = render_notation(tab, Notation::NoteNames, "richter", Style::Default, "A", 2);
+        assert_eq!(res, tab_to_note_names(tab, "richter", Style::Default, "A"));
+        let res = render_notation(tab, Notation::All, "richter", Style::Default, "A", 2);
+        assert_eq!(
+            res,
+            "-2 -3' 4\nE  G   A\n1  b3  4\n\n\n-4 (4 5)\nB  X\n5  X\n\n"
+        );
+        assert_eq!(Notation::from_str("notes"), Ok(Notation::NoteNames));
+    }
+
+    #[test]
+    fn test_exit_code() {
+        assert_eq!(exit_code(false, false), 0);
+        assert_eq!(exit_code(true, false), EXIT_INVALID_NOTES);
+        assert_eq!(exit_code(true, true), 3);
+        assert_eq!(count_unplayable_notes("X 4 X(4 5) -4"), 2);
+    }
+
+    #[test]
+    fn test_get_playable_positions_in_all_tunings() {
+        // 3 draw in 2nd position needs a bend on richter, but not on country or melody maker
+        let tab = "-2 -3' 4";
+        let res = get_playable_positions_in_all_tunings(
+            tab,
+            2,
+            "richter",
+            Style::Default,
+            &TechniqueProfile::bends(),
+            None,
+        );
+        assert!(res.windows(2).all(|w| w[0].difficulty <= w[1].difficulty));
+        let richter = res
+            .iter()
+            .find(|p| p.tuning == "richter" && p.semitones == 0)
+            .unwrap();
+        assert_eq!(richter.difficulty, 1);
+        assert_eq!(res[0].difficulty, 0);
+    }
+    #[test]
+    fn test_get_playback_notes() {
+        let res = get_playback_notes("4 -4\n asdf 5", "richter", Style::Default);
+        let expected = vec![
+            PlaybackNote {
+                index: 3,
+                chord: Vec::new(),
+                reeds: vec![Reed {
+                    hole: 4,
+                    draw: false,
+                }],
+                span: 0..1,
+            },
+            PlaybackNote {
+                index: 5,
+                chord: Vec::new(),
+                reeds: vec![Reed {
+                    hole: 4,
+                    draw: true,
+                }],
+                span: 2..4,
+            },
+            PlaybackNote {
+                index: 7,
+                chord: Vec::new(),
+                reeds: vec![Reed {
+                    hole: 5,
+                    draw: false,
+                }],
+                span: 11..12,
+            },
+        ];
+        assert_eq!(res, expected);
+
+        // 3 blow sounds like 2 draw, but on its own reed
+        let res = get_playback_notes("3 -2 -3''", "richter", Style::Default);
+        assert_eq!(res[0].index, res[1].index);
+        let reeds: Vec<Reed> = res.iter().map(|note| note.reeds[0]).collect();
+        assert_eq!(
+            reeds,
+            vec![
+                Reed::of("3").unwrap(),
+                Reed::of("-2").unwrap(),
+                Reed::of("-3").unwrap()
+            ]
+        );
+
+        let res = get_playback_notes_in_key("4", "richter", Style::Default, "A");
+        assert_eq!(res[0].index, 0);
+    }
+
+    #[test]
+    fn test_transpose_hole_groups() {
+        let tab = "(1 2 3) 456 [1 4] -2/-3 (4 6)".to_string();
+        let (res, errors) = transpose_tabs(tab, 12, false, Style::Default, "richter", "richter");
+        assert_eq!(res, "(4 5 6) 789 [4 7] X6/-7 X(7 9) \n");
+        assert!(errors.is_empty());
+
+        let (res, _) = transpose_tabs(
+            "(1 2 3)".to_string(),
+            0,
+            false,
+            Style::Default,
+            "richter",
+            "richter",
+        );
+        assert_eq!(res, "(1 2 3) \n");
+
+        let res = get_playback_notes("4 -456", "richter", Style::Default);
+        assert_eq!(res[1].index, 5);
+        assert_eq!(res[1].chord, vec![8, 12]);
+        assert_eq!(res[1].span, 2..6);
+    }
+
+    #[test]
+    fn test_transpose_ornaments() {
+        let tab = "-4v 1-4 gliss 4s -4~-5 -2tr".to_string();
+        let (res, errors) = transpose_tabs(tab, 12, false, Style::Default, "richter", "richter");
+        assert_eq!(res, "-8v 4-7 gliss 7s -8~-9 6tr \n");
+        assert!(errors.is_empty());
+
+        assert_eq!(change_tab_style_single("-3'v", Style::BBends), "-3bv");
+        assert_eq!(change_tab_style_single("4~5", Style::Plus), "+4~+5");
+
+        let res = get_playback_notes("-4v 1-4 gliss", "richter", Style::Default);
+        assert_eq!(res.len(), 2);
+        assert_eq!(res[0].index, 5);
+    }
+
     #[test]
     fn test_scale_to_tab() {
         let res = scale_to_tab("major", "richter", 1, Style::Default);
@@ -1,5 +1,15 @@
-use clap::{App, Arg};
-use harptabber::{run, RunOptions, Style};
+use clap::{App, Arg, ArgMatches, SubCommand};
+use harptabber::{
+    collect_tab_files, exit_code, get_scales, get_tabkeyboard_layout, get_tunings,
+    note_name_tokens, run, run_quiz, scale_degree_tokens, scale_to_tab, tab_to_note_names,
+    tab_to_scale_degrees, to_json, transpose_batch, AnswerKind, BackingOptions, BatchOptions,
+    BatchOutput, HoleRange, InputFormat, Metronome, Notation, OutputFormat, PracticeOptions,
+    Progression, QuestionKind, QuizOptions, RunOptions, Style, TechniqueProfile, Temperament,
+    TimeSignature, KEYS,
+};
+use std::path::Path;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 fn is_int(val: String) -> Result<(), String> {
     if val.parse::<i32>().is_ok() {
@@ -9,109 +19,673 @@ fn is_int(val: String) -> Result<(), String> {
     }
 }
 
+fn is_progression(val: String) -> Result<(), String> {
+    Progression::from_str(&val)
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+fn is_time_signature(val: String) -> Result<(), String> {
+    TimeSignature::from_str(&val)
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+fn is_frequency(val: String) -> Result<(), String> {
+    match val.parse::<f32>() {
+        Ok(n) if n > 0.0 => Ok(()),
+        _ => Err(String::from("arg must be a positive number")),
+    }
+}
+
+fn is_scale(val: String) -> Result<(), String> {
+    if harptabber::get_scales().contains_key(&val) {
+        Ok(())
+    } else {
+        let names: Vec<&str> = harptabber::get_scales()
+            .keys()
+            .map(|k| k.as_str())
+            .collect();
+        Err(format!("scale must be one of: {}", names.join(", ")))
+    }
+}
+
+fn is_style(val: String) -> Result<(), String> {
+    Style::from_str(&val).map(|_| ()).map_err(|e| e.to_string())
+}
+
+fn is_hole_range(val: String) -> Result<(), String> {
+    HoleRange::from_str(&val)
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+fn is_technique(val: String) -> Result<(), String> {
+    TechniqueProfile::from_str(&val)
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+fn is_uint(val: String) -> Result<(), String> {
+    if val.parse::<u32>().is_ok() {
+        Ok(())
+    } else {
+        Err(String::from("arg must be a non-negative integer"))
+    }
+}
+
+fn is_seed(val: String) -> Result<(), String> {
+    if val.parse::<u64>().is_ok() {
+        Ok(())
+    } else {
+        Err(String::from("seed must be a non-negative integer"))
+    }
+}
+
+fn is_positive_int(val: String) -> Result<(), String> {
+    match val.parse::<u32>() {
+        Ok(n) if n > 0 => Ok(()),
+        _ => Err(String::from("arg must be a positive integer")),
+    }
+}
+
+fn is_position(val: String) -> Result<(), String> {
+    match val.parse::<u32>() {
+        Ok(n) if (1..=12).contains(&n) => Ok(()),
+        _ => Err(String::from("position must be between 1 and 12")),
+    }
+}
+
+/// transpose arguments that only work on a single file
+const SINGLE_FILE_ARGS: [&str; 21] = [
+    "output",
+    "harptab",
+    "playable-positions",
+    "all-tunings",
+    "verbose",
+    "no-bends",
+    "technique",
+    "holes",
+    "split-octaves",
+    "play",
+    "bpm",
+    "backing",
+    "wav",
+    "temperament",
+    "reference-pitch",
+    "reed-offsets",
+    "transcribe",
+    "check",
+    "metronome",
+    "notation",
+    "input-format",
+];
+
+/// the arguments for transposing, playing and searching positions, also used without a subcommand
+fn transpose_args() -> Vec<Arg<'static, 'static>> {
+    vec![
+        Arg::with_name("semitones")
+            .short("s")
+            .long("semitones")
+            .value_name("SEMITONES")
+            .allow_hyphen_values(true)
+            .help("number of semitones to transpose")
+            .validator(is_int),
+        Arg::with_name("to-position")
+            .short("t")
+            .long("to")
+            .value_name("POSITION")
+            .help("position to transpose to")
+            .validator(is_position),
+        Arg::with_name("from-position")
+            .short("f")
+            .long("from")
+            .value_name("POSITION")
+            .help("position to transpose from [default: 1, or from the .harptab header]")
+            .validator(is_position),
+        Arg::with_name("octave-shift")
+            .short("o")
+            .long("octave")
+            .value_name("OCTAVES")
+            .allow_hyphen_values(true)
+            .default_value("0")
+            .help("octaves to shift resulting tab")
+            .validator(is_int),
+        Arg::with_name("keep-errors")
+            .short("e")
+            .long("keep-errors")
+            .help("include invalid notes in output tab"),
+        Arg::with_name("style")
+            .long("style")
+            .value_name("STYLE")
+            .help("set tab style (harpsurgery, b-bends, plus, draw, default)")
+            .validator(is_style),
+        Arg::with_name("input-tuning")
+            .long("input-tuning")
+            .value_name("TUNING")
+            .help("set tuning of input harp [default: richter, or from the .harptab header]"),
+        Arg::with_name("output-tuning")
+            .long("output-tuning")
+            .value_name("TUNING")
+            .default_value("richter")
+            .help("set tuning of output harp"),
+        Arg::with_name("file")
+            .value_name("FILE")
+            .multiple(true)
+            .help("file containing tabs, .harptab files can have a header with the key, position, tuning, style and tempo. several files, directories or glob patterns like 'songs/**/*.txt' are transposed as a batch [default: - (stdin)]"),
+        Arg::with_name("output")
+            .long("output")
+            .value_name("FILE")
+            .conflicts_with_all(&["output-dir", "write"])
+            .help("write the result to a file instead of stdout (there is no short flag, -o is --octave)"),
+        Arg::with_name("write")
+            .long("write")
+            .conflicts_with_all(&["output-dir", "playable-positions", "all-tunings", "transcribe"])
+            .help("change the input files in place, only when transposing to tab. keeps text that isn't a note, like -e"),
+        Arg::with_name("output-dir")
+            .long("output-dir")
+            .value_name("DIR")
+            .help("write the transposed files of a batch to a directory, mirroring the input tree"),
+        Arg::with_name("harptab")
+            .long("harptab")
+            .help("print the result as a .harptab file with a header (always done for .harptab input)"),
+        Arg::with_name("playable-positions")
+            .short("p")
+            .long("playable-positions")
+            .help("transpose to all playable positions (without overblows, or with the techniques of --technique)"),
+        Arg::with_name("all-tunings")
+            .long("all-tunings")
+            .help("list the playable positions on every tuning, easiest first (with --playable-positions or the positions subcommand)"),
+        Arg::with_name("verbose")
+            .short("v")
+            .long("verbose")
+            .help("list the bends, overblows and note range of every playable position (with --playable-positions or the positions subcommand)"),
+        Arg::with_name("no-bends")
+            .short("n")
+            .long("no-bends")
+            .help("disallow bends (with --playable-positions or --split-octaves)"),
+        Arg::with_name("technique")
+            .long("technique")
+            .value_name("PROFILE")
+            .conflicts_with("no-bends")
+            .help("techniques allowed with --playable-positions or --split-octaves: none, bends, all, or a list like draw-bends=1,draw-bends@2-3=2,blow-bends=1,overblows@4-6,overdraws,valved [default: bends]")
+            .validator(is_technique),
+        Arg::with_name("holes")
+            .long("holes")
+            .value_name("RANGE")
+            .allow_hyphen_values(true)
+            .help("keep the tab within a range of holes, like 4-7, or -3.. for nothing below 3 draw. picks the octave, or filters and ranks --playable-positions")
+            .validator(is_hole_range),
+        Arg::with_name("split-octaves")
+            .long("split-octaves")
+            .requires("to-position")
+            .help("choose an octave per line to keep the tab playable without overblows in the --to position, marking changes with %octave lines"),
+        Arg::with_name("play")
+            .short("a")
+            .long("play")
+            .help("play tab as audio"),
+        Arg::with_name("loop")
+            .long("loop")
+            .requires("play")
+            .help("loop the played notes, speeding up after every --loops-per-step loops and stopping once they were played at --target-bpm (Ctrl-C stops earlier)"),
+        Arg::with_name("from-note")
+            .long("from-note")
+            .value_name("NOTE")
+            .requires("play")
+            .help("first note to play (starting at 1)")
+            .validator(is_positive_int),
+        Arg::with_name("to-note")
+            .long("to-note")
+            .value_name("NOTE")
+            .requires("play")
+            .help("last note to play")
+            .validator(is_positive_int),
+        Arg::with_name("bpm")
+            .long("bpm")
+            .value_name("BPM")
+            .help("tempo in notes per minute (starting tempo with --loop) [default: 200, 60 with --loop, 90 with --backing or --metronome]")
+            .validator(is_positive_int),
+        Arg::with_name("target-bpm")
+            .long("target-bpm")
+            .value_name("BPM")
+            .requires("loop")
+            .help("tempo to speed up to (with --loop) [default: 120]")
+            .validator(is_positive_int),
+        Arg::with_name("bpm-step")
+            .long("bpm-step")
+            .value_name("BPM")
+            .requires("loop")
+            .help("tempo increase per step (with --loop) [default: 10]")
+            .validator(is_positive_int),
+        Arg::with_name("loops-per-step")
+            .long("loops-per-step")
+            .value_name("LOOPS")
+            .requires("loop")
+            .help("loops to play before speeding up (with --loop) [default: 2]")
+            .validator(is_positive_int),
+        Arg::with_name("count-in")
+            .long("count-in")
+            .value_name("BEATS")
+            .requires("loop")
+            .help("clicks before the first loop (with --loop) [default: 4]")
+            .validator(is_uint),
+        Arg::with_name("key")
+            .short("k")
+            .long("key")
+            .value_name("KEY")
+            .possible_values(&KEYS)
+            .help("key of the output harp, for audio and --check [default: C, or from the .harptab header]"),
+        Arg::with_name("backing")
+            .long("backing")
+            .value_name("PROGRESSION")
+            .help(
+                "backing track (drone, 12-bar, quick-change or roman numerals like \"I IV V\")",
+            )
+            .validator(is_progression),
+        Arg::with_name("beats-per-chord")
+            .long("beats-per-chord")
+            .value_name("BEATS")
+            .requires("backing")
+            .help("beats each chord of the backing track lasts [default: 4]")
+            .validator(is_positive_int),
+        Arg::with_name("with-tab")
+            .long("with-tab")
+            .requires("backing")
+            .help("mix the tab into the backing track"),
+        Arg::with_name("wav")
+            .long("wav")
+            .value_name("WAV_FILE")
+            .help("render audio to a wav file"),
+        Arg::with_name("temperament")
+            .long("temperament")
+            .value_name("TEMPERAMENT")
+            .default_value("equal")
+            .possible_values(&["equal", "just", "7-limit", "compromise"])
+            .help("temperament of the output harp, relative to its key, for audio"),
+        Arg::with_name("reference-pitch")
+            .long("reference-pitch")
+            .value_name("HZ")
+            .default_value("440")
+            .help("frequency of A4, for audio")
+            .validator(is_frequency),
+        Arg::with_name("reed-offsets")
+            .long("reed-offsets")
+            .value_name("FILE")
+            .help("file with measured cent offsets per reed, like \"-2 -4.5\" per line"),
+        Arg::with_name("transcribe")
+            .long("transcribe")
+            .value_name("KEY")
+            .possible_values(&KEYS)
+            .help("read FILE as a mono wav recording of a harp in KEY and transcribe it"),
+        Arg::with_name("check")
+            .long("check")
+            .value_name("WAV_FILE")
+            .conflicts_with_all(&["playable-positions", "all-tunings"])
+            .help("compare a mono wav recording of the output tab to it, listing cent deviations"),
+        Arg::with_name("metronome")
+            .long("metronome")
+            .help("click along with the tab, one note per beat"),
+        Arg::with_name("time-signature")
+            .long("time-signature")
+            .value_name("SIGNATURE")
+            .requires("metronome")
+            .help("time signature of the metronome, like 3/4 [default: 4/4]")
+            .validator(is_time_signature),
+        Arg::with_name("no-accent")
+            .long("no-accent")
+            .requires("metronome")
+            .help("don't accent the first beat of every bar"),
+        Arg::with_name("notation")
+            .long("notation")
+            .value_name("NOTATION")
+            .default_value("tab")
+            .possible_values(&["tab", "notes", "degrees", "all"])
+            .help("write the output as tab, note names on a harp in --key, scale degrees in the target position, or all three below each other"),
+        Arg::with_name("input-format")
+            .long("input-format")
+            .value_name("FORMAT")
+            .default_value("tab")
+            .possible_values(&["tab", "notes", "degrees"])
+            .help("read the input as tab, note names like 'G A B D' or 'G4 A4' on a harp in --key, or scale degrees like '1 b3 4 #4 5' in the --from position"),
+        format_arg(),
+    ]
+}
+
 fn main() {
     let matches = App::new("harptabber")
         .about("transpose harmonica tabs")
-        .arg(
-            Arg::with_name("semitones")
-                .short("s")
-                .long("semitones")
-                .value_name("SEMITONES")
-                .allow_hyphen_values(true)
-                .help("number of semitones to transpose")
-                .validator(is_int),
-        )
-        .arg(
-            Arg::with_name("to-position")
-                .short("t")
-                .long("to")
-                .value_name("POSITION")
-                .help("position to transpose to")
-                .validator(is_int),
-        )
-        .arg(
-            Arg::with_name("from-position")
-                .short("f")
-                .long("from")
-                .value_name("POSITION")
-                .default_value("1")
-                .help("position to transpose from")
-                .validator(is_int),
+        .args(&transpose_args())
+        .subcommand(
+            SubCommand::with_name("transpose")
+                .about("transpose a tab, the same as without a subcommand")
+                .args(&transpose_args()),
         )
-        .arg(
-            Arg::with_name("octave-shift")
-                .short("o")
-                .long("octave")
-                .value_name("OCTAVES")
-                .allow_hyphen_values(true)
-                .default_value("0")
-                .help("octaves to shift resulting tab")
-                .validator(is_int),
+        .subcommand(
+            SubCommand::with_name("positions")
+                .about("transpose a tab to all positions it is playable in")
+                .args(&transpose_args()),
         )
-        .arg(
-            Arg::with_name("keep-errors")
-                .short("e")
-                .long("keep-errors")
-                .help("include invalid notes in output tab"),
+        .subcommand(
+            SubCommand::with_name("play")
+                .about("play a tab as audio")
+                .args(&transpose_args()),
         )
-        .arg(
-            Arg::with_name("style")
-                .long("style")
-                .value_name("STYLE")
-                .help("set tab style (harpsurgery, b-bends, plus, draw, default)"),
+        .subcommand(
+            SubCommand::with_name("quiz")
+                .about("ear training: play a hole or an interval and ask to name it")
+                .arg(
+                    Arg::with_name("question")
+                        .value_name("QUESTION")
+                        .default_value("hole")
+                        .possible_values(&["hole", "interval"]),
+                )
+                .arg(
+                    Arg::with_name("answer")
+                        .long("answer")
+                        .value_name("ANSWER")
+                        .default_value("hole")
+                        .possible_values(&["hole", "degree", "note"])
+                        .help("what to name (degrees are relative to --position)"),
+                )
+                .arg(
+                    Arg::with_name("scale")
+                        .long("scale")
+                        .value_name("SCALE")
+                        .help("only ask for notes of this scale, played in --position")
+                        .validator(is_scale),
+                )
+                .arg(
+                    Arg::with_name("questions")
+                        .long("questions")
+                        .value_name("N")
+                        .default_value("10")
+                        .help("number of questions")
+                        .validator(is_positive_int),
+                )
+                .arg(
+                    Arg::with_name("seed")
+                        .long("seed")
+                        .value_name("SEED")
+                        .help("seed for the questions, to repeat a quiz [default: random, printed at the start]")
+                        .validator(is_seed),
+                )
+                .arg(key_arg())
+                .arg(position_arg())
+                .arg(tuning_arg()),
         )
-        .arg(
-            Arg::with_name("input-tuning")
-                .long("input-tuning")
-                .value_name("TUNING")
-                .default_value("richter")
-                .help("set tuning of input harp"),
+        .subcommand(
+            SubCommand::with_name("scale")
+                .about("print the tab of a scale")
+                .arg(
+                    Arg::with_name("scale")
+                        .value_name("SCALE")
+                        .required(true)
+                        .validator(is_scale),
+                )
+                .arg(position_arg())
+                .arg(tuning_arg())
+                .arg(style_arg())
+                .arg(format_arg()),
         )
-        .arg(
-            Arg::with_name("output-tuning")
-                .long("output-tuning")
-                .value_name("TUNING")
-                .default_value("richter")
-                .help("set tuning of output harp"),
+        .subcommand(
+            SubCommand::with_name("layout")
+                .about("print the notes of a harp, like the tab keyboard")
+                .arg(tuning_arg())
+                .arg(format_arg()),
         )
-        .arg(
-            Arg::with_name("file")
-                .value_name("FILE")
-                .help("file containing tabs")
-                .required(true),
+        .subcommand(
+            SubCommand::with_name("notes")
+                .about("print the note names of a tab")
+                .arg(input_arg())
+                .arg(key_arg())
+                .arg(tuning_arg())
+                .arg(style_arg())
+                .arg(format_arg()),
         )
-        .arg(
-            Arg::with_name("playable-positions")
-                .short("p")
-                .long("playable-positions")
-                .help("transpose to all playable positions (without overblows)"),
+        .subcommand(
+            SubCommand::with_name("degrees")
+                .about("print the scale degrees of a tab")
+                .arg(input_arg())
+                .arg(position_arg())
+                .arg(tuning_arg())
+                .arg(style_arg())
+                .arg(format_arg()),
         )
-        .arg(
-            Arg::with_name("no-bends")
-                .short("n")
-                .long("no-bends")
-                .help("disallow bends (with --playable-positions)"),
+        .subcommand(
+            SubCommand::with_name("tunings")
+                .about("list the known tunings")
+                .arg(format_arg()),
         )
-        .arg(
-            Arg::with_name("play")
-                .short("a")
-                .long("play")
-                .help("play tab as audio"),
+        .subcommand(
+            SubCommand::with_name("scales")
+                .about("list the known scales")
+                .arg(format_arg()),
         )
         .get_matches();
 
-    let filename = matches.value_of("file").unwrap();
+    match matches.subcommand() {
+        ("transpose", Some(matches)) => transpose(matches, Command::Transpose),
+        ("positions", Some(matches)) => transpose(matches, Command::Positions),
+        ("play", Some(matches)) => transpose(matches, Command::Play),
+        ("quiz", Some(matches)) => {
+            let options = QuizOptions {
+                question: QuestionKind::from_str(matches.value_of("question").unwrap()).unwrap(),
+                answer: AnswerKind::from_str(matches.value_of("answer").unwrap()).unwrap(),
+                tuning: matches.value_of("tuning").unwrap().to_string(),
+                key: matches.value_of("key").unwrap().to_string(),
+                scale: matches.value_of("scale").map(|s| s.to_string()),
+                position: matches
+                    .value_of("position")
+                    .unwrap()
+                    .parse::<u32>()
+                    .unwrap(),
+            };
+            let questions = matches
+                .value_of("questions")
+                .unwrap()
+                .parse::<u32>()
+                .unwrap();
+            let seed = matches.value_of("seed").map_or_else(
+                || {
+                    SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .unwrap()
+                        .as_nanos() as u64
+                },
+                |n| n.parse::<u64>().unwrap(),
+            );
+            run_quiz(options, questions, seed);
+        }
+        ("scale", Some(matches)) => {
+            let (tuning, style) = tuning_and_style(matches);
+            let position = matches
+                .value_of("position")
+                .unwrap()
+                .parse::<i32>()
+                .unwrap();
+            let scale = matches.value_of("scale").unwrap();
+            let tab = scale_to_tab(scale, tuning, position, style);
+            match output_format(matches) {
+                OutputFormat::Text => println!("{}", tab),
+                OutputFormat::Json => print!(
+                    "{}",
+                    to_json(&serde_json::json!({
+                        "scale": scale,
+                        "position": position,
+                        "tuning": tuning,
+                        "style": style,
+                        "tab": tab,
+                    }))
+                ),
+            }
+        }
+        ("layout", Some(matches)) => {
+            let layout = get_tabkeyboard_layout(matches.value_of("tuning").unwrap());
+            let rows: Vec<&Vec<String>> = layout
+                .iter()
+                .filter(|row| row.iter().any(|n| !n.is_empty()))
+                .collect();
+            match output_format(matches) {
+                OutputFormat::Text => {
+                    for row in rows {
+                        let row: Vec<String> =
+                            row.iter().map(|note| format!("{:>5}", note)).collect();
+                        println!("{}", row.concat().trim_end());
+                    }
+                }
+                OutputFormat::Json => print!("{}", to_json(&rows)),
+            }
+        }
+        ("notes", Some(matches)) => {
+            let (tuning, style) = tuning_and_style(matches);
+            let key = matches.value_of("key").unwrap();
+            let tab = read_input(matches.value_of("file").unwrap());
+            let tokens = note_name_tokens(&tab, tuning, style, key);
+            match output_format(matches) {
+                OutputFormat::Text => print!("{}", tab_to_note_names(&tab, tuning, style, key)),
+                OutputFormat::Json => print!("{}", to_json(&tokens)),
+            }
+            std::process::exit(exit_code(
+                tokens.iter().any(|token| token.output.is_none()),
+                false,
+            ));
+        }
+        ("degrees", Some(matches)) => {
+            let (tuning, style) = tuning_and_style(matches);
+            let position = matches
+                .value_of("position")
+                .unwrap()
+                .parse::<u32>()
+                .unwrap();
+            let tab = read_input(matches.value_of("file").unwrap());
+            let tokens = scale_degree_tokens(&tab, tuning, style, position);
+            match output_format(matches) {
+                OutputFormat::Text => {
+                    print!("{}", tab_to_scale_degrees(&tab, tuning, style, position))
+                }
+                OutputFormat::Json => print!("{}", to_json(&tokens)),
+            }
+            std::process::exit(exit_code(
+                tokens.iter().any(|token| token.output.is_none()),
+                false,
+            ));
+        }
+        ("tunings", Some(matches)) => match output_format(matches) {
+            OutputFormat::Text => {
+                for tuning in get_tunings() {
+                    println!("{}", tuning);
+                }
+            }
+            OutputFormat::Json => print!("{}", to_json(&get_tunings())),
+        },
+        ("scales", Some(matches)) => match output_format(matches) {
+            OutputFormat::Text => {
+                for (name, degrees) in get_scales() {
+                    println!("{}: {}", name, degrees.join(" "));
+                }
+            }
+            OutputFormat::Json => print!("{}", to_json(get_scales())),
+        },
+        _ => transpose(&matches, Command::Transpose),
+    }
+}
+
+/// what the transpose arguments are used for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Command {
+    Transpose,
+    Positions,
+    Play,
+}
+
+fn input_arg() -> Arg<'static, 'static> {
+    Arg::with_name("file")
+        .value_name("FILE")
+        .default_value("-")
+        .help("file containing tabs, - for stdin")
+}
+
+fn key_arg() -> Arg<'static, 'static> {
+    Arg::with_name("key")
+        .long("key")
+        .value_name("KEY")
+        .possible_values(&KEYS)
+        .default_value("C")
+        .help("key of the harp")
+}
+
+fn position_arg() -> Arg<'static, 'static> {
+    Arg::with_name("position")
+        .short("p")
+        .long("position")
+        .value_name("POSITION")
+        .default_value("1")
+        .help("position to play in")
+        .validator(is_positive_int)
+}
+
+fn tuning_arg() -> Arg<'static, 'static> {
+    Arg::with_name("tuning")
+        .long("tuning")
+        .value_name("TUNING")
+        .default_value("richter")
+        .help("tuning of the harp")
+}
+
+fn style_arg() -> Arg<'static, 'static> {
+    Arg::with_name("style")
+        .long("style")
+        .value_name("STYLE")
+        .default_value("default")
+        .help("tab style (harpsurgery, b-bends, plus, draw, default)")
+        .validator(is_style)
+}
+
+fn format_arg() -> Arg<'static, 'static> {
+    Arg::with_name("format")
+        .long("format")
+        .value_name("FORMAT")
+        .default_value("text")
+        .possible_values(&["text", "json"])
+        .help("print the result as text, or as json with the tokens, errors and positions")
+}
+
+fn output_format(matches: &ArgMatches) -> OutputFormat {
+    OutputFormat::from_str(matches.value_of("format").unwrap()).unwrap()
+}
+
+fn tuning_and_style<'a>(matches: &'a ArgMatches) -> (&'a str, Style) {
+    let style = Style::from_str(matches.value_of("style").unwrap()).unwrap();
+    (matches.value_of("tuning").unwrap(), style)
+}
+
+/// the contents of a file, or of stdin for `-`
+fn read_input(filename: &str) -> String {
+    let text = if filename == "-" {
+        std::io::read_to_string(std::io::stdin())
+    } else {
+        std::fs::read_to_string(filename)
+    };
+    text.unwrap_or_else(|_| {
+        eprintln!("could not read file");
+        std::process::exit(-1);
+    })
+}
+
+fn transpose(matches: &ArgMatches, command: Command) {
+    let inputs: Vec<&str> = matches
+        .values_of("file")
+        .map_or_else(|| vec!["-"], |files| files.collect());
     let semitones = matches.value_of("semitones").unwrap_or("0");
     let semitones = semitones.parse::<i32>().unwrap();
-    let input_tuning = matches.value_of("input-tuning").unwrap();
+    let input_tuning = matches.value_of("input-tuning");
     let output_tuning = matches.value_of("output-tuning").unwrap();
 
-    let keep_errors = matches.is_present("keep-errors");
+    // writing in place never drops the lyrics and chords around the notes
+    let keep_errors = matches.is_present("keep-errors") || matches.is_present("write");
     let from_position = matches
         .value_of("from-position")
-        .unwrap()
-        .parse::<i32>()
-        .unwrap();
+        .map(|n| n.parse::<i32>().unwrap());
     let octave_shift = matches
         .value_of("octave-shift")
         .unwrap()
@@ -123,21 +697,175 @@ fn main() {
         to_position = matches.value_of("to-position").unwrap().parse::<i32>().ok();
     }
 
-    let mut style = Style::Default;
-    if matches.is_present("style") {
-        style = match matches.value_of("style").unwrap() {
-            "b-bends" => Style::BBends,
-            "harpsurgery" => Style::Harpsurgery,
-            "plus" => Style::Plus,
-            "draw" => Style::DrawDefault,
-            _ => Style::Default,
+    let style = matches
+        .value_of("style")
+        .map(|s| Style::from_str(s).unwrap());
+
+    let play_audio = matches.is_present("play") || command == Command::Play;
+    let loop_playback = matches.is_present("loop");
+    let from_note = matches
+        .value_of("from-note")
+        .map(|n| n.parse::<usize>().unwrap());
+    let to_note = matches
+        .value_of("to-note")
+        .map(|n| n.parse::<usize>().unwrap());
+    let key = matches.value_of("key");
+
+    if matches.is_present("write") {
+        let changes_output = command != Command::Transpose
+            || output_format(matches) != OutputFormat::Text
+            || matches.value_of("notation") != Some("tab")
+            || matches.value_of("input-format") != Some("tab");
+        if changes_output {
+            eprintln!("--write only writes tab over tab, not json, notes, degrees, positions or with play");
+            std::process::exit(-1);
         }
     }
 
-    let play_audio = matches.is_present("play");
+    if command == Command::Positions && matches.is_present("check") {
+        eprintln!("--check compares a recording to one transposed tab, not to positions");
+        std::process::exit(-1);
+    }
+
+    let is_batch = inputs.len() > 1
+        || matches.is_present("output-dir")
+        || Path::new(inputs[0]).is_dir()
+        || (!Path::new(inputs[0]).is_file() && inputs[0].contains(['*', '?', '[']));
+    if is_batch {
+        if command != Command::Transpose {
+            eprintln!("several files can only be transposed, not played or searched for positions");
+            std::process::exit(-1);
+        }
+        // arguments with defaults only count when given
+        let unsupported: Vec<String> = SINGLE_FILE_ARGS
+            .iter()
+            .filter(|arg| matches.occurrences_of(arg) > 0)
+            .map(|arg| format!("--{}", arg))
+            .collect();
+        if !unsupported.is_empty() {
+            eprintln!(
+                "{} can't be used with several files, write them with --output-dir or --write",
+                unsupported.join(", ")
+            );
+            std::process::exit(-1);
+        }
+        let options = BatchOptions {
+            semitones,
+            from_position,
+            to_position,
+            octave_shift,
+            keep_errors,
+            style,
+            input_tuning,
+            output_tuning,
+            key,
+        };
+        let files = collect_tab_files(&inputs).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(-1);
+        });
+        let format = output_format(matches);
+        let output = match matches.value_of("output-dir") {
+            Some(dir) => BatchOutput::Dir(Path::new(dir)),
+            None if matches.is_present("write") => BatchOutput::InPlace,
+            None => BatchOutput::Collect,
+        };
+        let summary = transpose_batch(files, &options, output);
+        if matches!(output, BatchOutput::Collect) && format == OutputFormat::Text {
+            for result in summary.results.iter() {
+                if let Ok(transposed) = &result.result {
+                    print!(
+                        "==> {} <==\n{}\n",
+                        result.file.path.display(),
+                        transposed.text
+                    );
+                }
+            }
+        }
+        match format {
+            OutputFormat::Text => eprint!("{}", summary),
+            OutputFormat::Json => print!("{}", to_json(&summary)),
+        }
+        std::process::exit(summary.exit_code());
+    }
+
+    let filename = inputs[0];
+    let output_file = if matches.is_present("write") {
+        if filename == "-" {
+            eprintln!("can't write stdin in place");
+            std::process::exit(-1);
+        }
+        Some(filename)
+    } else {
+        matches.value_of("output")
+    };
+    let bpm = matches.value_of("bpm").map(|n| n.parse::<f64>().unwrap());
+    let harptab_output = matches.is_present("harptab");
+    let wav_file = matches.value_of("wav");
+    let backing_with_tab = matches.is_present("with-tab");
+    let backing = matches.value_of("backing").map(|progression| {
+        let defaults = BackingOptions::default();
+        BackingOptions {
+            progression: Progression::from_str(progression).unwrap(),
+            bpm: matches
+                .value_of("bpm")
+                .map_or(defaults.bpm, |n| n.parse::<f64>().unwrap()),
+            beats_per_chord: matches
+                .value_of("beats-per-chord")
+                .map_or(defaults.beats_per_chord, |n| n.parse::<u32>().unwrap()),
+        }
+    });
+
+    let metronome = matches.is_present("metronome").then(|| {
+        let defaults = Metronome::default();
+        Metronome {
+            bpm: match &backing {
+                Some(backing) => backing.bpm,
+                None => matches
+                    .value_of("bpm")
+                    .map_or(defaults.bpm, |n| n.parse::<f64>().unwrap()),
+            },
+            time_signature: matches
+                .value_of("time-signature")
+                .map_or(defaults.time_signature, |s| {
+                    TimeSignature::from_str(s).unwrap()
+                }),
+            accent_downbeat: !matches.is_present("no-accent"),
+        }
+    });
+
+    let temperament = Temperament::from_str(matches.value_of("temperament").unwrap()).unwrap();
+    let reference_pitch = matches
+        .value_of("reference-pitch")
+        .unwrap()
+        .parse::<f32>()
+        .unwrap();
+    let reed_offsets_file = matches.value_of("reed-offsets");
+    let transcribe_key = matches.value_of("transcribe");
+    let check_file = matches.value_of("check");
+
+    let defaults = PracticeOptions::default();
+    let practice = PracticeOptions {
+        start_bpm: matches
+            .value_of("bpm")
+            .map_or(defaults.start_bpm, |n| n.parse::<f64>().unwrap()),
+        target_bpm: matches
+            .value_of("target-bpm")
+            .map_or(defaults.target_bpm, |n| n.parse::<f64>().unwrap()),
+        bpm_step: matches
+            .value_of("bpm-step")
+            .map_or(defaults.bpm_step, |n| n.parse::<f64>().unwrap()),
+        loops_per_step: matches
+            .value_of("loops-per-step")
+            .map_or(defaults.loops_per_step, |n| n.parse::<u32>().unwrap()),
+        count_in: matches
+            .value_of("count-in")
+            .map_or(defaults.count_in, |n| n.parse::<u32>().unwrap()),
+    };
 
     let mut options = RunOptions {
         filename,
+        output_file,
         semitones,
         from_position,
         to_position,
@@ -148,13 +876,45 @@ fn main() {
         output_tuning,
         _play_audio: play_audio,
         playable_positions: false,
-        allow_bends: false,
+        all_tunings: matches.is_present("all-tunings"),
+        verbose: matches.is_present("verbose"),
+        split_octaves: false,
+        hole_range: matches
+            .value_of("holes")
+            .map(|range| HoleRange::from_str(range).unwrap()),
+        technique: TechniqueProfile::bends(),
+        loop_playback,
+        from_note,
+        to_note,
+        practice,
+        key,
+        bpm,
+        harptab_output,
+        backing,
+        backing_with_tab,
+        wav_file,
+        metronome,
+        temperament,
+        reference_pitch,
+        reed_offsets_file,
+        transcribe_key,
+        check_file,
+        format: output_format(matches),
+        notation: Notation::from_str(matches.value_of("notation").unwrap()).unwrap(),
+        input_format: InputFormat::from_str(matches.value_of("input-format").unwrap()).unwrap(),
     };
 
-    if matches.is_present("playable-positions") {
+    if matches.is_present("playable-positions") || command == Command::Positions {
         options.playable_positions = true;
-        options.allow_bends = !matches.is_present("no-bends");
+    }
+    if matches.is_present("split-octaves") {
+        options.split_octaves = true;
+    }
+    if let Some(technique) = matches.value_of("technique") {
+        options.technique = TechniqueProfile::from_str(technique).unwrap();
+    } else if matches.is_present("no-bends") {
+        options.technique = TechniqueProfile::none();
     }
 
-    run(options);
+    std::process::exit(run(options));
 }
@@ -0,0 +1,137 @@
+use crate::synth::Voice;
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// note index and length (in seconds) of metronome clicks
+const CLICK_INDEX: i32 = 24;
+const ACCENT_INDEX: i32 = 31;
+pub const CLICK_DURATION: f64 = 0.03;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum TimeSignatureError {
+    #[error("`{0}` is not a valid time signature")]
+    Invalid(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeSignature {
+    pub beats: u32,
+    pub unit: u32,
+}
+
+impl Default for TimeSignature {
+    fn default() -> Self {
+        Self { beats: 4, unit: 4 }
+    }
+}
+
+impl FromStr for TimeSignature {
+    type Err = TimeSignatureError;
+
+    /// parse a time signature like `3/4`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || TimeSignatureError::Invalid(s.to_string());
+        let (beats, unit) = s.split_once('/').ok_or_else(invalid)?;
+        let beats = beats.trim().parse::<u32>().map_err(|_| invalid())?;
+        let unit = unit.trim().parse::<u32>().map_err(|_| invalid())?;
+        if beats == 0 || !unit.is_power_of_two() {
+            return Err(invalid());
+        }
+        Ok(TimeSignature { beats, unit })
+    }
+}
+
+impl fmt::Display for TimeSignature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.beats, self.unit)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Metronome {
+    /// beats per minute, with one tab note per beat
+    pub bpm: f64,
+    pub time_signature: TimeSignature,
+    /// click the first beat of every bar at a higher pitch
+    pub accent_downbeat: bool,
+}
+
+impl Default for Metronome {
+    fn default() -> Self {
+        Self {
+            bpm: 90.0,
+            time_signature: TimeSignature::default(),
+            accent_downbeat: true,
+        }
+    }
+}
+
+impl Metronome {
+    /// length of a beat in seconds
+    pub fn beat_duration(&self) -> f64 {
+        60.0 / self.bpm
+    }
+
+    /// note index of the click on a beat, counting from the start of the first bar
+    pub fn click_index(&self, beat: usize) -> i32 {
+        if self.accent_downbeat && beat.is_multiple_of(self.time_signature.beats as usize) {
+            ACCENT_INDEX
+        } else {
+            CLICK_INDEX
+        }
+    }
+
+    /// clicks for every beat, lasting at least `min_beats` and always ending on a full bar
+    pub fn click_voices(&self, min_beats: usize) -> Vec<Voice> {
+        let bar = self.time_signature.beats as usize;
+        let beats = min_beats.div_ceil(bar).max(1) * bar;
+        (0..beats)
+            .map(|beat| Voice {
+                index: self.click_index(beat),
+                reed: None,
+                start: beat as f64 * self.beat_duration(),
+                duration: CLICK_DURATION,
+                amplitude: 0.15,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_time_signature() {
+        let res = TimeSignature::from_str("6/8");
+        assert_eq!(res, Ok(TimeSignature { beats: 6, unit: 8 }));
+        assert_eq!(res.unwrap().to_string(), "6/8");
+        assert!(TimeSignature::from_str("3/5").is_err());
+        assert!(TimeSignature::from_str("4").is_err());
+    }
+
+    #[test]
+    fn test_click_voices() {
+        let metronome = Metronome {
+            bpm: 120.0,
+            time_signature: TimeSignature { beats: 3, unit: 4 },
+            accent_downbeat: true,
+        };
+        let clicks = metronome.click_voices(4);
+        assert_eq!(clicks.len(), 6);
+        assert_eq!(clicks[3].start, 1.5);
+        let indices: Vec<i32> = clicks.iter().map(|c| c.index).collect();
+        assert_eq!(
+            indices,
+            vec![
+                ACCENT_INDEX,
+                CLICK_INDEX,
+                CLICK_INDEX,
+                ACCENT_INDEX,
+                CLICK_INDEX,
+                CLICK_INDEX
+            ]
+        );
+    }
+}
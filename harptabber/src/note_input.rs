@@ -0,0 +1,225 @@
+use crate::harptab::Directive;
+use crate::{change_tab_style, key_to_index_offset, tuning_to_notes_in_order, Style};
+use regex::Regex;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// semitones of the degrees of the major scale above the root
+const MAJOR_SCALE: [i32; 7] = [0, 2, 4, 5, 7, 9, 11];
+/// position in the notes of the first note without an octave, one octave above 1 blow
+const FIRST_NOTE: i32 = 12;
+
+/// what the input of a transposition is written in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum InputFormat {
+    #[default]
+    Tab,
+    /// note names like `G A B D` or `G4 A4`
+    NoteNames,
+    /// scale degrees like `1 b3 4 #4 5`
+    Degrees,
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+#[error("`{0}` is not a valid input format (expected tab, notes or degrees)")]
+pub struct InputFormatError(String);
+
+impl FromStr for InputFormat {
+    type Err = InputFormatError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "tab" => Ok(InputFormat::Tab),
+            "notes" => Ok(InputFormat::NoteNames),
+            "degrees" => Ok(InputFormat::Degrees),
+            _ => Err(InputFormatError(s.to_string())),
+        }
+    }
+}
+
+/// a note to find on the harp
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Pitch {
+    /// semitones above the key of the harp, in any octave
+    Class(i32),
+    /// position in the notes of the tuning
+    Exact(i32),
+}
+
+/// semitones of a note name above C, and its octave if it has one
+fn parse_note_name(name: &str) -> Option<(i32, Option<i32>)> {
+    lazy_static! {
+        static ref NOTE: Regex =
+            Regex::new(r"^(?P<letter>[A-Ga-g])(?P<accidental>#|b)?(?P<octave>-?\d)?$").unwrap();
+    }
+    let caps = NOTE.captures(name)?;
+    let letter = match caps["letter"].to_ascii_uppercase().as_str() {
+        "C" => 0,
+        "D" => 2,
+        "E" => 4,
+        "F" => 5,
+        "G" => 7,
+        "A" => 9,
+        _ => 11,
+    };
+    let accidental = match caps.name("accidental").map(|a| a.as_str()) {
+        Some("#") => 1,
+        Some(_) => -1,
+        None => 0,
+    };
+    let octave = caps.name("octave").map(|o| o.as_str().parse().unwrap());
+    Some((letter + accidental, octave))
+}
+
+/// write a melody as tab, finding every note `parse` gives a pitch for on the harp. notes without
+/// an octave are played as close as possible to the note before them. notes that aren't on the
+/// harp become `X`, tokens without a pitch are left out and returned. directive lines are kept
+fn melody_to_tab(
+    text: &str,
+    tuning: &str,
+    style: Style,
+    parse: impl Fn(&str) -> Option<Pitch>,
+) -> (String, Vec<String>) {
+    let (notes, _) = tuning_to_notes_in_order(tuning);
+    let notes = change_tab_style(&notes, style);
+    let on_harp = |position: i32| {
+        usize::try_from(position)
+            .ok()
+            .and_then(|p| notes.get(p))
+            .filter(|note| !note.is_empty() && note.as_str() != "X")
+    };
+
+    let mut previous = FIRST_NOTE;
+    let mut errors = Vec::new();
+    let result = text
+        .lines()
+        .map(|line| {
+            if Directive::parse(line).is_some() {
+                return line.to_string();
+            }
+            let mut res = String::new();
+            for token in line.split_whitespace() {
+                let position = match parse(token) {
+                    None => {
+                        errors.push(token.to_string());
+                        continue;
+                    }
+                    Some(Pitch::Exact(position)) => Some(position),
+                    Some(Pitch::Class(class)) => (0..notes.len() as i32)
+                        .filter(|&p| p.rem_euclid(12) == class && on_harp(p).is_some())
+                        .min_by_key(|&p| ((p - previous).abs(), p)),
+                };
+                match position.and_then(|p| on_harp(p).map(|note| (p, note))) {
+                    Some((position, note)) => {
+                        res.push_str(note);
+                        previous = position;
+                    }
+                    None => res.push('X'),
+                }
+                res.push(' ');
+            }
+            res
+        })
+        .chain(["".to_owned()])
+        .collect::<Vec<String>>()
+        .join("\n");
+    (result, errors)
+}
+
+/// a melody of note names like `G A B D` or `G4 A4 B4 D5` as tab on a harp in `key`.
+/// sharps and flats are both understood, whichever the key is spelled with
+pub fn note_names_to_tab(
+    text: &str,
+    tuning: &str,
+    style: Style,
+    key: &str,
+) -> (String, Vec<String>) {
+    let (key_semitones, _) = parse_note_name(key).unwrap_or((0, None));
+    let offset = key_to_index_offset(key);
+    melody_to_tab(text, tuning, style, |token| {
+        let (semitones, octave) = parse_note_name(token)?;
+        Some(match octave {
+            // 1 blow is in octave 4, less the offset of the key
+            Some(octave) => Pitch::Exact(semitones + 12 * (octave - 4) - offset),
+            None => Pitch::Class((semitones - key_semitones).rem_euclid(12)),
+        })
+    })
+}
+
+/// a melody of scale degrees like `1 b3 4 #4 5`, played in `position`, as tab
+pub fn degrees_to_tab(
+    text: &str,
+    tuning: &str,
+    style: Style,
+    position: u32,
+) -> (String, Vec<String>) {
+    lazy_static! {
+        static ref DEGREE: Regex = Regex::new(r"^(?P<accidental>b|#)?(?P<degree>[1-7])$").unwrap();
+    }
+    let root = ((position as i32 - 1) * 7).rem_euclid(12);
+    melody_to_tab(text, tuning, style, |token| {
+        let caps = DEGREE.captures(token)?;
+        let degree: usize = caps["degree"].parse().unwrap();
+        let accidental = match caps.name("accidental").map(|a| a.as_str()) {
+            Some("#") => 1,
+            Some(_) => -1,
+            None => 0,
+        };
+        Some(Pitch::Class(
+            (root + MAJOR_SCALE[degree - 1] + accidental).rem_euclid(12),
+        ))
+    })
+}
+
+/// the input as tab, converted from note names on a harp in `key` or degrees in `position`
+pub fn input_to_tab(
+    text: &str,
+    format: InputFormat,
+    tuning: &str,
+    style: Style,
+    key: &str,
+    position: u32,
+) -> (String, Vec<String>) {
+    match format {
+        InputFormat::Tab => (text.to_string(), Vec::new()),
+        InputFormat::NoteNames => note_names_to_tab(text, tuning, style, key),
+        InputFormat::Degrees => degrees_to_tab(text, tuning, style, position),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{tab_to_note_names, tab_to_scale_degrees};
+
+    #[test]
+    fn test_note_names_to_tab() {
+        let (tab, errors) = note_names_to_tab("G A B D\nF# Gb H", "richter", Style::Default, "G");
+        assert_eq!(tab, "4 -4 5 6 \n-7 -7 \n");
+        assert_eq!(errors, vec!["H"]);
+        assert_eq!(
+            tab_to_note_names(&tab, "richter", Style::Default, "G"),
+            "G A B D\nF# F#\n"
+        );
+
+        // octaves are absolute, 1 blow on a C harp is C4
+        let (tab, _) = note_names_to_tab("C4 C5 C6 C3", "richter", Style::Default, "C");
+        assert_eq!(tab, "1 4 7 X \n");
+        let (tab, _) = note_names_to_tab("A3 a4", "richter", Style::Default, "A");
+        assert_eq!(tab, "1 4 \n");
+    }
+
+    #[test]
+    fn test_degrees_to_tab() {
+        let (tab, errors) = degrees_to_tab("1 b3 4 #4 5 8", "richter", Style::Default, 2);
+        assert_eq!(tab, "-2 -3' 4 -4' -4 \n");
+        assert_eq!(errors, vec!["8"]);
+        assert_eq!(
+            tab_to_scale_degrees(&tab, "richter", Style::Default, 2),
+            "1 b3 4 #4 5\n"
+        );
+        let (tab, _) = degrees_to_tab("1\n%octave +1\n1", "richter", Style::Default, 1);
+        assert_eq!(tab, "4 \n%octave +1\n4 \n");
+        assert_eq!(InputFormat::from_str("degrees"), Ok(InputFormat::Degrees));
+    }
+}
@@ -0,0 +1,241 @@
+use crate::harptab::{body_lines, Directive, LineKind};
+use crate::{positions_to_semitones, Style, TechniqueProfile, Transposer};
+use serde::Serialize;
+use std::ops::RangeInclusive;
+
+/// octaves tried for every phrase, relative to the lowest shift into the target position
+const OCTAVES: RangeInclusive<i32> = -3..=2;
+
+/// a line of a tab transposed by its own number of octaves
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Phrase {
+    /// line in the tab, starting at 1
+    pub line: usize,
+    pub octaves: i32,
+    pub transposed: String,
+}
+
+/// a tab in a single position, with an octave shift chosen per phrase
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct OctaveSplit {
+    /// semitones of the shift into the target position, phrases are shifted by octaves from here
+    pub semitones: i32,
+    pub phrases: Vec<Phrase>,
+}
+
+impl OctaveSplit {
+    /// the transposed tab, with a `%%` comment before every change of octave.
+    /// the phrases are already shifted, so directives that would shift them again are left out.
+    /// other lines that aren't phrases are kept as they are
+    pub fn tab(&self, tab: &str) -> String {
+        let mut phrases = self.phrases.iter().peekable();
+        let mut octaves = 0;
+        let mut res = String::new();
+        for (i, line) in body_lines(tab, Style::Default).into_iter().enumerate() {
+            match phrases.next_if(|phrase| phrase.line == i + 1) {
+                Some(phrase) => {
+                    if phrase.octaves != octaves {
+                        octaves = phrase.octaves;
+                        let comment = Directive::Comment(format!("octave {:+}", octaves));
+                        res.push_str(&format!("{}\n", comment));
+                    }
+                    res.push_str(&phrase.transposed);
+                }
+                None => match line.kind {
+                    LineKind::Directive(
+                        Directive::Transpose(_) | Directive::Octave(_) | Directive::Position(_),
+                    ) => continue,
+                    _ => res.push_str(line.text),
+                },
+            }
+            res.push('\n');
+        }
+        res
+    }
+
+    /// lines where the octave changes, along with the new octave
+    pub fn changes(&self) -> Vec<(usize, i32)> {
+        let mut octaves = 0;
+        self.phrases
+            .iter()
+            .filter(|phrase| {
+                let changed = phrase.octaves != octaves;
+                octaves = phrase.octaves;
+                changed
+            })
+            .map(|phrase| (phrase.line, phrase.octaves))
+            .collect()
+    }
+}
+
+/// transpose a tab into `to_position`, choosing an octave for every line so the tab can be played
+/// with the techniques of a profile with as few octave changes as possible.
+/// lines are shifted and styled by the directives before them like `transpose_body` does, the octave
+/// of a line is relative to that.
+/// on failure, returns the lines that can't be played in any octave
+#[allow(clippy::too_many_arguments)]
+pub fn split_octaves(
+    tab: &str,
+    from_position: u32,
+    to_position: u32,
+    input_tuning: &str,
+    output_tuning: &str,
+    style: Style,
+    technique: &TechniqueProfile,
+) -> Result<OctaveSplit, Vec<usize>> {
+    let semitones = positions_to_semitones(from_position as i32, to_position as i32, 0);
+
+    // the playable transpositions of every phrase
+    let mut options: Vec<(usize, Vec<(i32, String)>)> = Vec::new();
+    for (i, line) in body_lines(tab, style).into_iter().enumerate() {
+        let LineKind::Tab(shift, line_style) = line.kind else {
+            continue;
+        };
+        if line.text.trim().is_empty() {
+            continue;
+        }
+        let transposer = Transposer::new(line.text, line_style, input_tuning, output_tuning);
+        let allowed = transposer.allowed(technique);
+        let total = shift.total(semitones, from_position);
+        let playable = OCTAVES
+            .filter(|octaves| transposer.is_playable(total + 12 * octaves, &allowed))
+            .map(|octaves| {
+                let (transposed, _) = transposer.transpose(total + 12 * octaves, false);
+                (octaves, transposed.trim_end().to_string())
+            })
+            .collect();
+        options.push((i + 1, playable));
+    }
+
+    let unplayable: Vec<usize> = options
+        .iter()
+        .filter(|(_, playable)| playable.is_empty())
+        .map(|(line, _)| *line)
+        .collect();
+    if !unplayable.is_empty() {
+        return Err(unplayable);
+    }
+
+    // fewest octave changes, then the smallest shifts. costs[i][j] is the best cost
+    // with phrase i in its j-th option, along with the option of the previous phrase
+    let mut costs: Vec<Vec<((usize, i32), usize)>> = Vec::new();
+    for (i, (_, playable)) in options.iter().enumerate() {
+        let row = playable
+            .iter()
+            .map(|(octaves, _)| {
+                if i == 0 {
+                    return ((0, octaves.abs()), 0);
+                }
+                costs[i - 1]
+                    .iter()
+                    .zip(options[i - 1].1.iter())
+                    .enumerate()
+                    .map(|(j, (((changes, shift), _), (previous_octaves, _)))| {
+                        let change = usize::from(previous_octaves != octaves);
+                        ((changes + change, shift + octaves.abs()), j)
+                    })
+                    .min()
+                    .unwrap()
+            })
+            .collect();
+        costs.push(row);
+    }
+
+    let mut phrases = Vec::new();
+    if let Some(last) = costs.last() {
+        let mut j = (0..last.len()).min_by_key(|&j| last[j].0).unwrap();
+        for i in (0..options.len()).rev() {
+            let (line, playable) = &options[i];
+            let (octaves, transposed) = playable[j].clone();
+            phrases.push(Phrase {
+                line: *line,
+                octaves,
+                transposed,
+            });
+            j = costs[i][j].1;
+        }
+        phrases.reverse();
+    }
+
+    Ok(OctaveSplit { semitones, phrases })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{transpose_body, HarpTab};
+    use std::str::FromStr;
+
+    #[test]
+    fn test_split_octaves() {
+        // the low phrase needs bends in 2nd position unless it goes up an octave
+        let tab = "4 5 6 7\n\n1 -1 2\n4 5 6 7\n";
+        let split = split_octaves(
+            tab,
+            1,
+            2,
+            "richter",
+            "richter",
+            Style::Default,
+            &TechniqueProfile::none(),
+        )
+        .unwrap();
+        assert_eq!(split.semitones, 7);
+        let octaves: Vec<i32> = split.phrases.iter().map(|p| p.octaves).collect();
+        assert_eq!(octaves, vec![0, 1, 0]);
+        assert_eq!(split.changes(), vec![(3, 1), (4, 0)]);
+        assert_eq!(
+            split.tab(tab),
+            "6 -7 -8 9\n\n%% octave +1\n6 -6 -7\n%% octave +0\n6 -7 -8 9\n"
+        );
+
+        // reading the split tab again doesn't shift it a second time
+        let harptab = HarpTab::from_str(&split.tab(tab)).unwrap();
+        let (transposed, errors) = transpose_body(
+            &harptab.body,
+            0,
+            2,
+            false,
+            Style::Default,
+            "richter",
+            "richter",
+        );
+        assert!(errors.is_empty());
+        let notes = |tab: &str| tab.split_whitespace().map(String::from).collect::<Vec<_>>();
+        assert_eq!(notes(&transposed), notes(&split.tab(tab)));
+
+        let res = split_octaves(
+            "4 -3'",
+            1,
+            1,
+            "richter",
+            "richter",
+            Style::Default,
+            &TechniqueProfile::none(),
+        );
+        assert_eq!(res, Err(vec![1]));
+    }
+
+    #[test]
+    fn test_split_octaves_directives() {
+        // the chorus is written an octave down and a fifth up, and switches to plus style
+        let tab = "4 5 6\n%octave -1\n%transpose 7\n%style plus\n+4 +5 +6\n";
+        let split = split_octaves(
+            tab,
+            1,
+            1,
+            "richter",
+            "richter",
+            Style::Default,
+            &TechniqueProfile::none(),
+        )
+        .unwrap();
+        let transposed: Vec<&str> = split
+            .phrases
+            .iter()
+            .map(|p| p.transposed.as_str())
+            .collect();
+        assert_eq!(transposed, vec!["4 5 6", "-2 -3 -4"]);
+        assert_eq!(split.tab(tab), "4 5 6\n%style plus\n-2 -3 -4\n");
+    }
+}
@@ -0,0 +1,246 @@
+use crate::{change_tab_style_single, fix_enharmonics, Style};
+use regex::Regex;
+
+/// words in tabs that describe the notes around them, kept as they are
+pub const ARTICULATION_WORDS: [&str; 5] = ["gliss", "shake", "slap", "trill", "vib"];
+
+/// suffixes marking an ornament on a single note
+const SUFFIXES: [(&str, Ornament); 3] = [
+    ("tr", Ornament::Trill),
+    ("v", Ornament::Vibrato),
+    ("s", Ornament::TongueSlap),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Ornament {
+    /// `-4v`
+    Vibrato,
+    /// `-4tr`
+    Trill,
+    /// `4s`
+    TongueSlap,
+    /// `-4~-5`, alternating with a neighbouring hole
+    Shake(String),
+    /// `1-4`, usually followed by `gliss`
+    Glissando(String),
+}
+
+/// a note carrying an ornament
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrnamentedNote {
+    pub note: String,
+    pub ornament: Ornament,
+}
+
+impl OrnamentedNote {
+    /// split a token like `-4v` or `4~5` into note and ornament, without checking the notes
+    pub fn split(token: &str) -> Option<Self> {
+        lazy_static! {
+            static ref SHAKE: Regex = Regex::new(r"^(?P<note>[^~]+)~(?P<other>[^~]+)$").unwrap();
+            static ref GLISS: Regex =
+                Regex::new(r"^(?P<note>-?[^-~]+)-(?P<other>-?[^-~]+)$").unwrap();
+            static ref NOTE_END: Regex = Regex::new(r"[\dBD'bo#]$").unwrap();
+        }
+
+        if let Some(caps) = SHAKE.captures(token) {
+            return Some(OrnamentedNote {
+                note: caps["note"].to_string(),
+                ornament: Ornament::Shake(caps["other"].to_string()),
+            });
+        }
+        if let Some(caps) = GLISS.captures(token) {
+            return Some(OrnamentedNote {
+                note: caps["note"].to_string(),
+                ornament: Ornament::Glissando(caps["other"].to_string()),
+            });
+        }
+        SUFFIXES.iter().find_map(|(suffix, ornament)| {
+            token
+                .strip_suffix(suffix)
+                .filter(|note| NOTE_END.is_match(note))
+                .map(|note| OrnamentedNote {
+                    note: note.to_string(),
+                    ornament: ornament.clone(),
+                })
+        })
+    }
+
+    /// split a token into note and ornament, `None` unless all notes are on a harp with `notes`
+    pub fn parse(token: &str, notes: &[String], duplicated_notes: &[String]) -> Option<Self> {
+        let ornamented = Self::split(token)?;
+        let is_note = |note: &str| {
+            notes
+                .iter()
+                .any(|n| n == fix_enharmonics(note, duplicated_notes))
+        };
+        let valid = is_note(&ornamented.note)
+            && match &ornamented.ornament {
+                Ornament::Shake(other) | Ornament::Glissando(other) => is_note(other),
+                _ => true,
+            };
+        valid.then_some(ornamented)
+    }
+
+    /// apply `f` to every note, e.g. to change the tab style
+    pub fn map(&self, f: impl Fn(&str) -> String) -> Self {
+        let ornament = match &self.ornament {
+            Ornament::Shake(other) => Ornament::Shake(f(other)),
+            Ornament::Glissando(other) => Ornament::Glissando(f(other)),
+            ornament => ornament.clone(),
+        };
+        OrnamentedNote {
+            note: f(&self.note),
+            ornament,
+        }
+    }
+
+    pub fn format(&self) -> String {
+        match &self.ornament {
+            Ornament::Shake(other) => format!("{}~{}", self.note, other),
+            Ornament::Glissando(other) => format!("{}-{}", self.note, other),
+            ornament => {
+                let (suffix, _) = SUFFIXES.iter().find(|(_, o)| o == ornament).unwrap();
+                format!("{}{}", self.note, suffix)
+            }
+        }
+    }
+
+    /// transpose the note and keep its ornament. shakes are re-derived on the output harp,
+    /// using the next hole up in the same direction.
+    /// `output_notes` and `output_duplicated_notes` are in the default style
+    pub fn transpose(
+        &self,
+        input_notes: &[String],
+        input_duplicated_notes: &[String],
+        output_notes: &[String],
+        output_duplicated_notes: &[String],
+        semitones: i32,
+        style: Style,
+    ) -> String {
+        let position = |note: &str, notes: &[String], duplicated_notes: &[String]| {
+            let note = fix_enharmonics(note, duplicated_notes);
+            notes.iter().position(|n| n == note).map(|p| p as i32)
+        };
+        let transpose_note = |note: &str| {
+            let pos = position(note, input_notes, input_duplicated_notes).unwrap() + semitones;
+            usize::try_from(pos)
+                .ok()
+                .and_then(|pos| output_notes.get(pos))
+                .cloned()
+                .unwrap_or_else(|| String::from("X"))
+        };
+
+        let note = transpose_note(&self.note);
+        if note == "X" {
+            return note;
+        }
+        let transposed = match &self.ornament {
+            Ornament::Shake(other) => {
+                let interval = position(other, input_notes, input_duplicated_notes).unwrap()
+                    - position(&self.note, input_notes, input_duplicated_notes).unwrap();
+
+                // duplicated notes give a choice of holes to shake from
+                let mut candidates = vec![note.clone()];
+                for pair in output_duplicated_notes.chunks(2) {
+                    if pair.len() == 2 && pair[1] == note {
+                        candidates.push(pair[0].clone());
+                    }
+                }
+                let shakes: Vec<(String, String)> = candidates
+                    .into_iter()
+                    .filter_map(|note| {
+                        let other = shake_partner(&note, output_notes, output_duplicated_notes)?;
+                        Some((note, other))
+                    })
+                    .collect();
+                let same_interval = shakes.iter().find(|(note, other)| {
+                    let pos = |n: &str| position(n, output_notes, output_duplicated_notes);
+                    pos(other).zip(pos(note)).map(|(o, n)| o - n) == Some(interval)
+                });
+                match same_interval.or(shakes.first()) {
+                    Some((note, other)) => OrnamentedNote {
+                        note: note.clone(),
+                        ornament: Ornament::Shake(other.clone()),
+                    },
+                    None => OrnamentedNote {
+                        note,
+                        ornament: Ornament::Shake(String::from("X")),
+                    },
+                }
+            }
+            Ornament::Glissando(other) => OrnamentedNote {
+                note,
+                ornament: Ornament::Glissando(transpose_note(other)),
+            },
+            ornament => OrnamentedNote {
+                note,
+                ornament: ornament.clone(),
+            },
+        };
+        transposed
+            .map(|n| change_tab_style_single(n, style))
+            .format()
+    }
+}
+
+/// the hole a note (in the default style) is shaken with: the next hole up in the same direction,
+/// or the one below for the top hole
+fn shake_partner(note: &str, notes: &[String], duplicated_notes: &[String]) -> Option<String> {
+    lazy_static! {
+        static ref RE: Regex = Regex::new(r"^(?P<dir>-?)(?P<hole>\d{1,2})(?P<rest>.*)$").unwrap();
+    }
+
+    let caps = RE.captures(note)?;
+    let hole = caps["hole"].parse::<i32>().unwrap();
+    let exists = |n: &String| notes.contains(n) || duplicated_notes.contains(n);
+    [hole + 1, hole - 1].iter().find_map(|other| {
+        let bent = format!("{}{}{}", &caps["dir"], other, &caps["rest"]);
+        let natural = format!("{}{}", &caps["dir"], other);
+        [bent, natural].into_iter().find(exists)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tuning_to_notes_in_order;
+
+    #[test]
+    fn test_split_ornament() {
+        let note = OrnamentedNote::split("-4v").unwrap();
+        assert_eq!(note.note, "-4");
+        assert_eq!(note.ornament, Ornament::Vibrato);
+        assert_eq!(note.format(), "-4v");
+
+        let note = OrnamentedNote::split("1-4").unwrap();
+        assert_eq!(note.ornament, Ornament::Glissando(String::from("4")));
+        let note = OrnamentedNote::split("-3'~-4").unwrap();
+        assert_eq!(note.note, "-3'");
+
+        assert_eq!(OrnamentedNote::split("-4"), None);
+        assert_eq!(OrnamentedNote::split("-2''"), None);
+        assert_eq!(OrnamentedNote::split("tr"), None);
+    }
+
+    #[test]
+    fn test_transpose_shake() {
+        let (notes, duplicated) = tuning_to_notes_in_order("richter");
+        let shake = OrnamentedNote::parse("-4~-5", &notes, &duplicated).unwrap();
+        // up a fourth only the blow 6 plays G
+        let res = shake.transpose(&notes, &duplicated, &notes, &duplicated, 5, Style::Default);
+        assert_eq!(res, "6~7");
+        let res = shake.transpose(&notes, &duplicated, &notes, &duplicated, -7, Style::Default);
+        assert_eq!(res, "-2~-3");
+
+        let shake = OrnamentedNote::parse("3~4", &notes, &duplicated).unwrap();
+        let res = shake.transpose(
+            &notes,
+            &duplicated,
+            &notes,
+            &duplicated,
+            0,
+            Style::Harpsurgery,
+        );
+        assert_eq!(res, "3B~4B");
+    }
+}
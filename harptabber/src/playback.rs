@@ -0,0 +1,510 @@
+use crate::metronome::{Metronome, CLICK_DURATION as METRONOME_CLICK_DURATION};
+use crate::temperament::Reed;
+use std::ops::Range;
+
+/// length of a single note in seconds
+pub const NOTE_DURATION: f64 = 0.3;
+
+/// note index and length (in seconds) of count-in clicks
+const CLICK_INDEX: i32 = 24;
+const CLICK_DURATION: f64 = 0.05;
+
+/// a single note of a tab, ready to be played
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlaybackNote {
+    /// note index (with 0 = A440)
+    pub index: i32,
+    /// further notes sounding together with `index`, for chords
+    pub chord: Vec<i32>,
+    /// reeds of `index` and then of `chord`, empty for notes that weren't read from a tab
+    pub reeds: Vec<Reed>,
+    /// byte range of the note in the tab it was read from
+    pub span: Range<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlaybackEvent {
+    NoteStart {
+        note: usize,
+        span: Range<usize>,
+    },
+    NoteEnd {
+        note: usize,
+        span: Range<usize>,
+    },
+    /// a count-in click, starting at 0
+    CountIn {
+        beat: u32,
+    },
+    /// the last note of a looped section ended, playback continues at its start
+    LoopEnd,
+    Finished,
+}
+
+/// something notes can be played on, e.g. a rodio sink
+pub trait AudioOutput {
+    /// current time in seconds, relative to an arbitrary starting point
+    fn now(&self) -> f64;
+    /// start playing a note on `reed` right away
+    fn play_note(&mut self, index: i32, reed: Option<Reed>, duration: f64);
+    /// start playing mono samples (at `SAMPLE_RATE`) right away
+    fn play_samples(&mut self, samples: &[f32]);
+    /// silence everything that is currently playing
+    fn stop(&mut self);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    Stopped,
+    Playing {
+        note: usize,
+        started_at: f64,
+    },
+    CountIn {
+        beat: u32,
+        next: usize,
+        started_at: f64,
+    },
+    Paused {
+        note: usize,
+    },
+}
+
+/// plays a list of notes one at a time, reporting note starts and ends through `update`
+pub struct Player<O: AudioOutput> {
+    output: O,
+    notes: Vec<PlaybackNote>,
+    note_duration: f64,
+    looping: bool,
+    section: Option<Range<usize>>,
+    count_in: u32,
+    metronome: Option<Metronome>,
+    /// beats since playback (or the current loop) started, for metronome accents
+    beat: usize,
+    state: State,
+    events: Vec<PlaybackEvent>,
+}
+
+impl<O: AudioOutput> Player<O> {
+    pub fn new(output: O) -> Self {
+        Self {
+            output,
+            notes: Vec::new(),
+            note_duration: NOTE_DURATION,
+            looping: false,
+            section: None,
+            count_in: 0,
+            metronome: None,
+            beat: 0,
+            state: State::Stopped,
+            events: Vec::new(),
+        }
+    }
+
+    pub fn output(&self) -> &O {
+        &self.output
+    }
+
+    pub fn output_mut(&mut self) -> &mut O {
+        &mut self.output
+    }
+
+    /// replace the notes to play, stopping playback
+    pub fn set_notes(&mut self, notes: Vec<PlaybackNote>) {
+        self.stop();
+        self.notes = notes;
+        self.section = None;
+    }
+
+    /// only play (and loop) the notes in a range, or all notes if `None`
+    pub fn set_section(&mut self, section: Option<Range<usize>>) {
+        self.section = section;
+    }
+
+    /// the range of notes that is played
+    pub fn section(&self) -> Range<usize> {
+        let len = self.notes.len();
+        match &self.section {
+            Some(section) => section.start.min(len)..section.end.min(len),
+            None => 0..len,
+        }
+    }
+
+    /// number of clicks to play before playback starts
+    pub fn set_count_in(&mut self, beats: u32) {
+        self.count_in = beats;
+    }
+
+    /// click along with every note, setting the note duration to the metronome's tempo
+    pub fn set_metronome(&mut self, metronome: Option<Metronome>) {
+        if let Some(metronome) = &metronome {
+            self.note_duration = metronome.beat_duration();
+        }
+        self.metronome = metronome;
+    }
+
+    pub fn metronome(&self) -> Option<&Metronome> {
+        self.metronome.as_ref()
+    }
+
+    pub fn notes(&self) -> &[PlaybackNote] {
+        &self.notes
+    }
+
+    pub fn set_note_duration(&mut self, seconds: f64) {
+        self.note_duration = seconds;
+    }
+
+    pub fn set_looping(&mut self, looping: bool) {
+        self.looping = looping;
+    }
+
+    pub fn is_looping(&self) -> bool {
+        self.looping
+    }
+
+    pub fn is_playing(&self) -> bool {
+        matches!(self.state, State::Playing { .. } | State::CountIn { .. })
+    }
+
+    pub fn is_paused(&self) -> bool {
+        matches!(self.state, State::Paused { .. })
+    }
+
+    /// index of the note that is playing (or would continue playing when paused)
+    pub fn current_note(&self) -> Option<usize> {
+        match self.state {
+            State::Playing { note, .. } | State::Paused { note } => Some(note),
+            State::CountIn { .. } | State::Stopped => None,
+        }
+    }
+
+    /// start playing from the start of the section (after counting in), or continue if paused
+    pub fn play(&mut self) {
+        match self.state {
+            State::Paused { note } => self.start_note(note),
+            State::Playing { .. } | State::CountIn { .. } => (),
+            State::Stopped => {
+                let section = self.section();
+                if section.is_empty() {
+                    return;
+                }
+                self.beat = 0;
+                if self.count_in > 0 {
+                    self.start_click(0, section.start);
+                } else {
+                    self.start_note(section.start);
+                }
+            }
+        }
+    }
+
+    pub fn pause(&mut self) {
+        match self.state {
+            State::Playing { note, .. } => {
+                self.output.stop();
+                self.end_note(note);
+                self.state = State::Paused { note };
+            }
+            State::CountIn { next, .. } => {
+                self.output.stop();
+                self.state = State::Paused { note: next };
+            }
+            State::Paused { .. } | State::Stopped => (),
+        }
+    }
+
+    pub fn resume(&mut self) {
+        if let State::Paused { note } = self.state {
+            self.start_note(note);
+        }
+    }
+
+    pub fn stop(&mut self) {
+        match self.state {
+            State::Playing { note, .. } => {
+                self.output.stop();
+                self.end_note(note);
+            }
+            State::CountIn { .. } => self.output.stop(),
+            State::Paused { .. } => (),
+            State::Stopped => return,
+        }
+        self.state = State::Stopped;
+    }
+
+    /// jump to a note, keeping the current play/pause state
+    pub fn seek(&mut self, note: usize) {
+        if note >= self.notes.len() {
+            self.stop();
+            return;
+        }
+        match self.state {
+            State::Playing { note: current, .. } => {
+                self.output.stop();
+                self.end_note(current);
+                self.start_note(note);
+            }
+            State::CountIn {
+                beat, started_at, ..
+            } => {
+                self.state = State::CountIn {
+                    beat,
+                    next: note,
+                    started_at,
+                };
+            }
+            State::Paused { .. } | State::Stopped => {
+                self.state = State::Paused { note };
+            }
+        }
+    }
+
+    /// advance playback, returning everything that happened since the last call
+    pub fn update(&mut self) -> Vec<PlaybackEvent> {
+        let now = self.output.now();
+        match self.state {
+            State::Playing { note, started_at } if now - started_at >= self.note_duration => {
+                self.end_note(note);
+                let section = self.section();
+                if note + 1 < section.end {
+                    self.start_note(note + 1);
+                } else if self.looping && !section.is_empty() {
+                    self.events.push(PlaybackEvent::LoopEnd);
+                    self.beat = 0;
+                    self.start_note(section.start);
+                } else {
+                    self.state = State::Stopped;
+                    self.events.push(PlaybackEvent::Finished);
+                }
+            }
+            State::CountIn {
+                beat,
+                next,
+                started_at,
+            } if now - started_at >= self.note_duration => {
+                if beat + 1 < self.count_in {
+                    self.start_click(beat + 1, next);
+                } else {
+                    self.start_note(next);
+                }
+            }
+            _ => (),
+        }
+        std::mem::take(&mut self.events)
+    }
+
+    fn start_click(&mut self, beat: u32, next: usize) {
+        self.output.play_note(CLICK_INDEX, None, CLICK_DURATION);
+        self.state = State::CountIn {
+            beat,
+            next,
+            started_at: self.output.now(),
+        };
+        self.events.push(PlaybackEvent::CountIn { beat });
+    }
+
+    fn start_note(&mut self, note: usize) {
+        let PlaybackNote {
+            index,
+            chord,
+            reeds,
+            span,
+        } = self.notes[note].clone();
+        if let Some(metronome) = &self.metronome {
+            let click = metronome.click_index(self.beat);
+            self.output.play_note(click, None, METRONOME_CLICK_DURATION);
+        }
+        self.beat += 1;
+        self.output
+            .play_note(index, reeds.first().copied(), self.note_duration);
+        for (i, index) in chord.into_iter().enumerate() {
+            self.output
+                .play_note(index, reeds.get(i + 1).copied(), self.note_duration);
+        }
+        self.state = State::Playing {
+            note,
+            started_at: self.output.now(),
+        };
+        self.events.push(PlaybackEvent::NoteStart { note, span });
+    }
+
+    fn end_note(&mut self, note: usize) {
+        let span = self.notes[note].span.clone();
+        self.events.push(PlaybackEvent::NoteEnd { note, span });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    struct TestOutput {
+        time: Rc<Cell<f64>>,
+        played: Vec<i32>,
+    }
+
+    impl AudioOutput for TestOutput {
+        fn now(&self) -> f64 {
+            self.time.get()
+        }
+        fn play_note(&mut self, index: i32, _reed: Option<Reed>, _duration: f64) {
+            self.played.push(index);
+        }
+        fn play_samples(&mut self, _samples: &[f32]) {}
+        fn stop(&mut self) {}
+    }
+
+    fn test_player() -> (Player<TestOutput>, Rc<Cell<f64>>) {
+        let time = Rc::new(Cell::new(0.0));
+        let output = TestOutput {
+            time: time.clone(),
+            played: Vec::new(),
+        };
+        let mut player = Player::new(output);
+        player.set_notes(vec![
+            PlaybackNote {
+                index: -9,
+                chord: Vec::new(),
+                reeds: Vec::new(),
+                span: 0..1,
+            },
+            PlaybackNote {
+                index: -7,
+                chord: Vec::new(),
+                reeds: Vec::new(),
+                span: 2..4,
+            },
+        ]);
+        (player, time)
+    }
+
+    #[test]
+    fn test_player_events() {
+        let (mut player, time) = test_player();
+        player.play();
+        assert_eq!(
+            player.update(),
+            vec![PlaybackEvent::NoteStart {
+                note: 0,
+                span: 0..1
+            }]
+        );
+
+        time.set(0.1);
+        assert!(player.update().is_empty());
+
+        time.set(0.3);
+        assert_eq!(
+            player.update(),
+            vec![
+                PlaybackEvent::NoteEnd {
+                    note: 0,
+                    span: 0..1
+                },
+                PlaybackEvent::NoteStart {
+                    note: 1,
+                    span: 2..4
+                },
+            ]
+        );
+
+        time.set(0.6);
+        assert_eq!(
+            player.update(),
+            vec![
+                PlaybackEvent::NoteEnd {
+                    note: 1,
+                    span: 2..4
+                },
+                PlaybackEvent::Finished,
+            ]
+        );
+        assert!(!player.is_playing());
+        assert_eq!(player.output().played, vec![-9, -7]);
+    }
+
+    #[test]
+    fn test_player_pause_seek_loop() {
+        let (mut player, time) = test_player();
+        player.set_looping(true);
+        player.play();
+        player.pause();
+        assert!(player.is_paused());
+        assert_eq!(player.current_note(), Some(0));
+
+        player.seek(1);
+        time.set(5.0);
+        player.update();
+        assert_eq!(player.current_note(), Some(1));
+
+        player.resume();
+        time.set(5.5);
+        let events = player.update();
+        assert_eq!(
+            events.last(),
+            Some(&PlaybackEvent::NoteStart {
+                note: 0,
+                span: 0..1
+            })
+        );
+        assert!(player.is_playing());
+        assert!(events.contains(&PlaybackEvent::LoopEnd));
+        assert_eq!(player.output().played, vec![-9, -7, -9]);
+    }
+
+    #[test]
+    fn test_player_count_in_and_section() {
+        let (mut player, time) = test_player();
+        player.set_section(Some(1..2));
+        player.set_count_in(2);
+        player.play();
+        assert_eq!(player.update(), vec![PlaybackEvent::CountIn { beat: 0 }]);
+
+        time.set(0.5);
+        assert_eq!(player.update(), vec![PlaybackEvent::CountIn { beat: 1 }]);
+
+        time.set(1.0);
+        assert_eq!(
+            player.update(),
+            vec![PlaybackEvent::NoteStart {
+                note: 1,
+                span: 2..4
+            }]
+        );
+
+        time.set(1.5);
+        assert_eq!(
+            player.update(),
+            vec![
+                PlaybackEvent::NoteEnd {
+                    note: 1,
+                    span: 2..4
+                },
+                PlaybackEvent::Finished,
+            ]
+        );
+        assert_eq!(player.output().played, vec![CLICK_INDEX, CLICK_INDEX, -7]);
+    }
+
+    #[test]
+    fn test_player_metronome() {
+        let (mut player, time) = test_player();
+        let metronome = Metronome {
+            bpm: 120.0,
+            time_signature: "2/4".parse().unwrap(),
+            accent_downbeat: true,
+        };
+        player.set_metronome(Some(metronome));
+        player.play();
+        time.set(0.5);
+        player.update();
+
+        let accent = metronome.click_index(0);
+        let click = metronome.click_index(1);
+        assert_ne!(accent, click);
+        assert_eq!(player.output().played, vec![accent, -9, click, -7]);
+    }
+}
@@ -0,0 +1,129 @@
+/// settings for looping a section while gradually speeding up
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PracticeOptions {
+    /// tempo of the first loops, in notes per minute
+    pub start_bpm: f64,
+    /// tempo to work up to
+    pub target_bpm: f64,
+    /// how much faster to get after `loops_per_step` clean loops
+    pub bpm_step: f64,
+    pub loops_per_step: u32,
+    /// number of clicks before the first loop
+    pub count_in: u32,
+}
+
+impl Default for PracticeOptions {
+    fn default() -> Self {
+        Self {
+            start_bpm: 60.0,
+            target_bpm: 120.0,
+            bpm_step: 10.0,
+            loops_per_step: 2,
+            count_in: 4,
+        }
+    }
+}
+
+/// keeps track of loops played and the resulting tempo
+#[derive(Debug, Clone, PartialEq)]
+pub struct PracticeSession {
+    options: PracticeOptions,
+    bpm: f64,
+    clean_loops: u32,
+    total_loops: u32,
+}
+
+impl PracticeSession {
+    pub fn new(options: PracticeOptions) -> Self {
+        Self {
+            options,
+            bpm: options.start_bpm.min(options.target_bpm),
+            clean_loops: 0,
+            total_loops: 0,
+        }
+    }
+
+    pub fn options(&self) -> &PracticeOptions {
+        &self.options
+    }
+
+    pub fn bpm(&self) -> f64 {
+        self.bpm
+    }
+
+    /// length of a single note at the current tempo, in seconds
+    pub fn note_duration(&self) -> f64 {
+        60.0 / self.bpm
+    }
+
+    /// clean loops played at the current tempo
+    pub fn clean_loops(&self) -> u32 {
+        self.clean_loops
+    }
+
+    pub fn total_loops(&self) -> u32 {
+        self.total_loops
+    }
+
+    pub fn reached_target(&self) -> bool {
+        self.bpm >= self.options.target_bpm
+    }
+
+    /// whether the target tempo was played cleanly `loops_per_step` times
+    pub fn finished(&self) -> bool {
+        self.reached_target() && self.clean_loops >= self.options.loops_per_step
+    }
+
+    /// register a finished loop, returning true if the tempo went up.
+    /// a loop with mistakes starts the count of clean loops over
+    pub fn loop_finished(&mut self, clean: bool) -> bool {
+        self.total_loops += 1;
+        if !clean {
+            self.clean_loops = 0;
+            return false;
+        }
+
+        self.clean_loops += 1;
+        if self.clean_loops < self.options.loops_per_step || self.reached_target() {
+            return false;
+        }
+        self.clean_loops = 0;
+        self.bpm = (self.bpm + self.options.bpm_step).min(self.options.target_bpm);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_practice_session() {
+        let mut session = PracticeSession::new(PracticeOptions {
+            start_bpm: 60.0,
+            target_bpm: 75.0,
+            bpm_step: 10.0,
+            loops_per_step: 2,
+            count_in: 0,
+        });
+        assert_eq!(session.note_duration(), 1.0);
+
+        assert!(!session.loop_finished(true));
+        assert!(!session.loop_finished(false));
+        assert!(!session.loop_finished(true));
+        assert!(session.loop_finished(true));
+        assert_eq!(session.bpm(), 70.0);
+
+        assert!(!session.loop_finished(true));
+        assert!(session.loop_finished(true));
+        assert_eq!(session.bpm(), 75.0);
+        assert!(session.reached_target());
+
+        assert!(!session.loop_finished(true));
+        assert!(!session.finished());
+        assert!(!session.loop_finished(true));
+        assert!(session.finished());
+        assert_eq!(session.bpm(), 75.0);
+        assert_eq!(session.total_loops(), 8);
+    }
+}
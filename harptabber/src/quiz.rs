@@ -0,0 +1,394 @@
+use crate::{
+    fix_enharmonics, get_playback_notes_in_key, get_scales, tab_to_note, tab_to_scale_degree,
+    tuning_to_notes_in_order, Style,
+};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum QuizError {
+    #[error("`{0}` is not a valid question kind (expected hole or interval)")]
+    InvalidQuestionKind(String),
+    #[error("`{0}` is not a valid answer kind (expected hole, degree or note)")]
+    InvalidAnswerKind(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuestionKind {
+    /// a single hole
+    Hole,
+    /// two holes, the first one is given
+    Interval,
+}
+
+impl FromStr for QuestionKind {
+    type Err = QuizError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "hole" => Ok(QuestionKind::Hole),
+            "interval" => Ok(QuestionKind::Interval),
+            _ => Err(QuizError::InvalidQuestionKind(s.to_string())),
+        }
+    }
+}
+
+/// what the user has to name
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnswerKind {
+    Hole,
+    ScaleDegree,
+    Note,
+}
+
+impl FromStr for AnswerKind {
+    type Err = QuizError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "hole" => Ok(AnswerKind::Hole),
+            "degree" => Ok(AnswerKind::ScaleDegree),
+            "note" => Ok(AnswerKind::Note),
+            _ => Err(QuizError::InvalidAnswerKind(s.to_string())),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuizOptions {
+    pub question: QuestionKind,
+    pub answer: AnswerKind,
+    pub tuning: String,
+    /// key of the harp
+    pub key: String,
+    /// only ask for notes of this scale, otherwise any unbent hole
+    pub scale: Option<String>,
+    /// position the scale and scale degrees are relative to
+    pub position: u32,
+}
+
+impl Default for QuizOptions {
+    fn default() -> Self {
+        Self {
+            question: QuestionKind::Hole,
+            answer: AnswerKind::Hole,
+            tuning: String::from("richter"),
+            key: String::from("C"),
+            scale: None,
+            position: 1,
+        }
+    }
+}
+
+/// xorshift64* generator, so quizzes can be repeated with the same seed
+#[derive(Debug, Clone)]
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        // xorshift gets stuck on 0
+        Self(if seed == 0 {
+            0x9e37_79b9_7f4a_7c15
+        } else {
+            seed
+        })
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Question {
+    /// holes to play, the last one has to be named
+    pub holes: Vec<String>,
+    /// note indices of the holes (with 0 = A440)
+    pub indices: Vec<i32>,
+    /// the first note of an interval, named like the answer
+    pub given: Option<String>,
+    pub answer: String,
+}
+
+/// correct and total answers
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Score {
+    pub correct: u32,
+    pub total: u32,
+}
+
+impl Score {
+    pub fn accuracy(&self) -> f32 {
+        if self.total == 0 {
+            0.0
+        } else {
+            self.correct as f32 / self.total as f32
+        }
+    }
+
+    fn add(&mut self, correct: bool) {
+        self.total += 1;
+        if correct {
+            self.correct += 1;
+        }
+    }
+}
+
+pub struct QuizSession {
+    options: QuizOptions,
+    rng: Rng,
+    notes: Vec<String>,
+    duplicated_notes: Vec<String>,
+    /// holes questions are picked from, with their note index
+    pool: Vec<(String, i32)>,
+    current: Option<Question>,
+    /// whether `current` has been answered, so that it only counts once
+    answered: bool,
+    score: Score,
+    per_hole: BTreeMap<String, Score>,
+}
+
+impl QuizSession {
+    pub fn new(options: QuizOptions, seed: u64) -> Self {
+        let (notes, duplicated_notes) = tuning_to_notes_in_order(&options.tuning);
+        let degrees = options.scale.as_ref().and_then(|s| get_scales().get(s));
+
+        let pool = notes
+            .iter()
+            .filter(|hole| *hole != "X")
+            .filter(|hole| match degrees {
+                Some(degrees) => {
+                    let degree =
+                        tab_to_scale_degree(hole, options.position, &notes, &duplicated_notes);
+                    degrees.contains(&degree)
+                }
+                None => !hole.contains('\'') && !hole.contains('o'),
+            })
+            .filter_map(|hole| {
+                let played =
+                    get_playback_notes_in_key(hole, &options.tuning, Style::Default, &options.key);
+                played.first().map(|note| (hole.clone(), note.index))
+            })
+            .collect();
+
+        Self {
+            options,
+            rng: Rng::new(seed),
+            notes,
+            duplicated_notes,
+            pool,
+            current: None,
+            answered: false,
+            score: Score::default(),
+            per_hole: BTreeMap::new(),
+        }
+    }
+
+    pub fn options(&self) -> &QuizOptions {
+        &self.options
+    }
+
+    pub fn current(&self) -> Option<&Question> {
+        self.current.as_ref()
+    }
+
+    pub fn score(&self) -> Score {
+        self.score
+    }
+
+    /// scores by the hole that had to be named
+    pub fn per_hole(&self) -> &BTreeMap<String, Score> {
+        &self.per_hole
+    }
+
+    /// pick a new question, `None` if the scale has no notes on the harp
+    pub fn next_question(&mut self) -> Option<&Question> {
+        if self.pool.is_empty() {
+            return None;
+        }
+        let first = self.pool[self.rng.below(self.pool.len())].clone();
+        let mut holes = vec![first.clone()];
+
+        if self.options.question == QuestionKind::Interval {
+            let candidates: Vec<&(String, i32)> = self
+                .pool
+                .iter()
+                .filter(|(_, index)| *index != first.1 && (index - first.1).abs() <= 12)
+                .collect();
+            if !candidates.is_empty() {
+                let second = candidates[self.rng.below(candidates.len())].clone();
+                holes.push(second);
+            }
+        }
+
+        let given = (holes.len() > 1).then(|| self.describe(&holes[0].0));
+        let answer = self.describe(&holes.last().unwrap().0);
+        self.current = Some(Question {
+            given,
+            indices: holes.iter().map(|(_, index)| *index).collect(),
+            holes: holes.into_iter().map(|(hole, _)| hole).collect(),
+            answer,
+        });
+        self.answered = false;
+        self.current.as_ref()
+    }
+
+    /// check an answer to the current question and update the score.
+    /// every question is scored once, later answers are `false` until the next question
+    pub fn answer(&mut self, guess: &str) -> bool {
+        let Some(question) = self.current.as_ref().filter(|_| !self.answered) else {
+            return false;
+        };
+        let correct = self.matches(guess.trim(), &question.answer);
+        let hole = question.holes.last().unwrap().clone();
+        self.answered = true;
+        self.score.add(correct);
+        self.per_hole.entry(hole).or_default().add(correct);
+        correct
+    }
+
+    /// a hole as it has to be named
+    fn describe(&self, hole: &str) -> String {
+        match self.options.answer {
+            AnswerKind::Hole => hole.to_string(),
+            AnswerKind::ScaleDegree => tab_to_scale_degree(
+                hole,
+                self.options.position,
+                &self.notes,
+                &self.duplicated_notes,
+            )
+            .to_string(),
+            AnswerKind::Note => {
+                tab_to_note(hole, &self.options.key, &self.notes, &self.duplicated_notes)
+                    .to_string()
+            }
+        }
+    }
+
+    fn matches(&self, guess: &str, answer: &str) -> bool {
+        match self.options.answer {
+            AnswerKind::Hole => {
+                fix_enharmonics(guess, &self.duplicated_notes)
+                    == fix_enharmonics(answer, &self.duplicated_notes)
+            }
+            AnswerKind::ScaleDegree => {
+                degree_to_semitones(guess).is_some()
+                    && degree_to_semitones(guess) == degree_to_semitones(answer)
+            }
+            AnswerKind::Note => {
+                note_to_semitones(guess).is_some()
+                    && note_to_semitones(guess) == note_to_semitones(answer)
+            }
+        }
+    }
+}
+
+/// apply a leading or trailing `b`/`#` to a number of semitones
+fn with_accidentals(semitones: i32, accidentals: &str) -> Option<i32> {
+    accidentals.chars().try_fold(semitones, |acc, c| match c {
+        'b' => Some(acc - 1),
+        '#' => Some(acc + 1),
+        _ => None,
+    })
+}
+
+/// semitones above the root of a scale degree like `b3` or `#4`
+fn degree_to_semitones(degree: &str) -> Option<i32> {
+    let number_start = degree.find(|c: char| c.is_ascii_digit())?;
+    let (accidentals, number) = degree.split_at(number_start);
+    let steps = [0, 2, 4, 5, 7, 9, 11];
+    let number = number
+        .parse::<usize>()
+        .ok()
+        .filter(|n| (1..=7).contains(n))?;
+    with_accidentals(steps[number - 1], accidentals).map(|s| s.rem_euclid(12))
+}
+
+/// semitones above C of a note name like `Eb` or `f#`
+fn note_to_semitones(note: &str) -> Option<i32> {
+    let mut chars = note.chars();
+    let letter = chars.next()?.to_ascii_uppercase();
+    let semitones = match letter {
+        'C' => 0,
+        'D' => 2,
+        'E' => 4,
+        'F' => 5,
+        'G' => 7,
+        'A' => 9,
+        'B' => 11,
+        _ => return None,
+    };
+    with_accidentals(semitones, chars.as_str()).map(|s| s.rem_euclid(12))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quiz_is_seedable() {
+        let options = QuizOptions {
+            question: QuestionKind::Interval,
+            ..QuizOptions::default()
+        };
+        let mut a = QuizSession::new(options.clone(), 42);
+        let mut b = QuizSession::new(options, 42);
+        for _ in 0..10 {
+            let question = a.next_question().unwrap().clone();
+            assert_eq!(Some(&question), b.next_question());
+            assert_eq!(question.holes.len(), 2);
+            assert!((question.indices[0] - question.indices[1]).abs() <= 12);
+            assert!(question.holes.iter().all(|h| !h.contains('\'')));
+        }
+    }
+
+    #[test]
+    fn test_quiz_answers() {
+        let options = QuizOptions {
+            answer: AnswerKind::Note,
+            key: String::from("G"),
+            scale: Some(String::from("major")),
+            position: 2,
+            ..QuizOptions::default()
+        };
+        let mut quiz = QuizSession::new(options, 7);
+        let question = quiz.next_question().unwrap().clone();
+        let degree =
+            tab_to_scale_degree(&question.holes[0], 2, &quiz.notes, &quiz.duplicated_notes);
+        assert!(!degree.starts_with('b') && degree != "#4");
+
+        assert!(!quiz.answer("H"));
+        // answering twice counts once
+        assert!(!quiz.answer(&question.answer.to_lowercase()));
+        assert_eq!(
+            quiz.score(),
+            Score {
+                correct: 0,
+                total: 1
+            }
+        );
+        assert_eq!(quiz.per_hole()[&question.holes[0]].total, 1);
+
+        let question = quiz.next_question().unwrap().clone();
+        assert!(quiz.answer(&question.answer.to_lowercase()));
+        assert_eq!(
+            quiz.score(),
+            Score {
+                correct: 1,
+                total: 2
+            }
+        );
+
+        assert_eq!(note_to_semitones("Db"), note_to_semitones("c#"));
+        assert_eq!(degree_to_semitones("#4"), degree_to_semitones("b5"));
+        assert_eq!(degree_to_semitones("8"), None);
+    }
+}
@@ -0,0 +1,216 @@
+use crate::harptab::{body_lines, LineKind};
+use crate::{
+    semitones_to_position, tab_difficulty, Style, TechniqueInventory, TransposedToken, Transposer,
+};
+use serde::Serialize;
+use std::ops::Range;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// how results are printed on the command line
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+#[error("`{0}` is not a valid output format (expected text or json)")]
+pub struct OutputFormatError(String);
+
+impl FromStr for OutputFormat {
+    type Err = OutputFormatError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            _ => Err(OutputFormatError(s.to_string())),
+        }
+    }
+}
+
+/// a value as pretty printed json, ending in a newline
+pub fn to_json<T: Serialize + ?Sized>(value: &T) -> String {
+    let mut json = serde_json::to_string_pretty(value).expect("results serialize to json");
+    json.push('\n');
+    json
+}
+
+/// a token of the input that couldn't be transposed, or a note that isn't on the output harp
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TokenError {
+    pub token: String,
+    /// line in the tab, starting at 1
+    pub line: usize,
+    /// byte range in the tab, in the body below the header of `.harptab` files
+    pub span: Range<usize>,
+    pub reason: String,
+}
+
+/// a transposed tab along with every token of the input and what went wrong
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TranspositionReport {
+    pub semitones: i32,
+    /// position of the output, for a tab in `from_position`
+    pub position: u32,
+    pub input_tuning: String,
+    pub output_tuning: String,
+    pub style: Style,
+    /// the output as printed
+    pub tab: String,
+    pub tokens: Vec<TransposedToken>,
+    pub errors: Vec<TokenError>,
+}
+
+impl TranspositionReport {
+    /// the report for a tab body transposed into `tab`. lines are shifted and styled by the
+    /// directives before them like `transpose_body` does, directive lines have no tokens.
+    /// lines and spans are counted in the body, below the header of `.harptab` files
+    pub fn new(
+        body: &str,
+        tab: String,
+        semitones: i32,
+        from_position: u32,
+        style: Style,
+        input_tuning: &str,
+        output_tuning: &str,
+    ) -> Self {
+        let mut tokens = Vec::new();
+        let mut errors = Vec::new();
+        for (i, line) in body_lines(body, style).into_iter().enumerate() {
+            let start = line.start;
+            match line.kind {
+                LineKind::Directive(_) => (),
+                LineKind::InvalidDirective(text) => errors.push(TokenError {
+                    token: text,
+                    line: i + 1,
+                    span: start..start + line.text.len(),
+                    reason: String::from("not a valid directive"),
+                }),
+                LineKind::Tab(shift, line_style) => {
+                    let transposer =
+                        Transposer::new(line.text, line_style, input_tuning, output_tuning);
+                    let total = shift.total(semitones, from_position);
+                    tokens.extend(transposer.tokens(|_| Some(total)).into_iter().map(|token| {
+                        TransposedToken {
+                            line: i + 1,
+                            span: start + token.span.start..start + token.span.end,
+                            ..token
+                        }
+                    }));
+                }
+            }
+        }
+
+        for token in tokens.iter() {
+            let reason = match &token.output {
+                None => format!("not a note on a {} harp", input_tuning),
+                Some(output) if output.contains('X') => {
+                    format!("can't be played on a {} harp", output_tuning)
+                }
+                Some(_) => continue,
+            };
+            errors.push(TokenError {
+                token: token.input.clone(),
+                line: token.line,
+                span: token.span.clone(),
+                reason,
+            });
+        }
+        errors.sort_by_key(|error| error.span.start);
+
+        TranspositionReport {
+            semitones,
+            position: semitones_to_position(from_position, semitones),
+            input_tuning: input_tuning.to_string(),
+            output_tuning: output_tuning.to_string(),
+            style,
+            tab,
+            tokens,
+            errors,
+        }
+    }
+}
+
+/// a position a tab is playable in, with how hard it is
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PositionReport {
+    pub tuning: String,
+    pub position: u32,
+    pub semitones: i32,
+    /// see `tab_difficulty`
+    pub difficulty: u32,
+    pub techniques: TechniqueInventory,
+    pub tab: String,
+}
+
+impl PositionReport {
+    /// the report for a tab shifted by `semitones` into `position` on a harp with `tuning`
+    pub fn new(
+        tab: &str,
+        position: u32,
+        semitones: i32,
+        style: Style,
+        input_tuning: &str,
+        tuning: &str,
+    ) -> Self {
+        let (transposed, _) =
+            Transposer::new(tab, style, input_tuning, tuning).transpose(semitones, true);
+        PositionReport {
+            tuning: tuning.to_string(),
+            position,
+            semitones,
+            difficulty: tab_difficulty(&transposed, tuning, style),
+            techniques: TechniqueInventory::new(&transposed, tuning, style),
+            tab: transposed,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transposition_report() {
+        let body = "4 asdf\n%octave +1\n10 6\n";
+        let report = TranspositionReport::new(
+            body,
+            String::new(),
+            0,
+            1,
+            Style::Default,
+            "richter",
+            "richter",
+        );
+        let outputs: Vec<Option<&str>> =
+            report.tokens.iter().map(|t| t.output.as_deref()).collect();
+        assert_eq!(outputs, vec![Some("4"), None, Some("X"), Some("9")]);
+        assert_eq!(report.tokens[2].line, 3);
+        assert_eq!(report.tokens[2].span, 18..20);
+        let reasons: Vec<(&str, &str)> = report
+            .errors
+            .iter()
+            .map(|e| (e.token.as_str(), e.reason.as_str()))
+            .collect();
+        assert_eq!(
+            reasons,
+            vec![
+                ("asdf", "not a note on a richter harp"),
+                ("10", "can't be played on a richter harp"),
+            ]
+        );
+
+        let json = to_json(&report);
+        assert!(json.contains("\"span\": {"));
+        assert!(json.contains("\"style\": \"default\""));
+    }
+
+    #[test]
+    fn test_output_format() {
+        assert_eq!(OutputFormat::from_str("json"), Ok(OutputFormat::Json));
+        assert!(OutputFormat::from_str("yaml").is_err());
+    }
+}
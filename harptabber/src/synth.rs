@@ -0,0 +1,97 @@
+use crate::playback::PlaybackNote;
+use crate::temperament::{Intonation, Reed};
+use std::path::Path;
+
+pub const SAMPLE_RATE: u32 = 44100;
+
+/// fade in/out time of every note, to avoid clicks
+const FADE_SECONDS: f64 = 0.005;
+
+/// a sine tone placed on a timeline
+#[derive(Debug, Clone, PartialEq)]
+pub struct Voice {
+    /// note index (with 0 = A440)
+    pub index: i32,
+    /// the reed playing it, for its offset
+    pub reed: Option<Reed>,
+    /// start time in seconds
+    pub start: f64,
+    /// length in seconds
+    pub duration: f64,
+    pub amplitude: f32,
+}
+
+/// frequency in Hz of a note index (with 0 = A440), in equal temperament
+pub fn index_to_frequency(index: i32) -> f32 {
+    f32::powf(2.0, index as f32 / 12.0) * 440.0
+}
+
+/// place notes one after another, each lasting `note_duration` seconds
+pub fn melody_voices(notes: &[PlaybackNote], note_duration: f64) -> Vec<Voice> {
+    notes
+        .iter()
+        .enumerate()
+        .flat_map(|(i, note)| {
+            // chords are as loud as single notes
+            let amplitude = 0.20 / (note.chord.len() + 1) as f32;
+            std::iter::once(note.index)
+                .chain(note.chord.iter().copied())
+                .enumerate()
+                .map(move |(j, index)| Voice {
+                    index,
+                    reed: note.reeds.get(j).copied(),
+                    start: i as f64 * note_duration,
+                    duration: note_duration,
+                    amplitude,
+                })
+        })
+        .collect()
+}
+
+/// mix voices into mono samples at `SAMPLE_RATE`
+pub fn render(voices: &[Voice]) -> Vec<f32> {
+    render_with_intonation(voices, &Intonation::default())
+}
+
+/// mix voices into mono samples at `SAMPLE_RATE`, tuned with `intonation`
+pub fn render_with_intonation(voices: &[Voice], intonation: &Intonation) -> Vec<f32> {
+    let end = voices
+        .iter()
+        .map(|v| v.start + v.duration)
+        .fold(0.0, f64::max);
+    let mut samples = vec![0.0; (end * SAMPLE_RATE as f64).ceil() as usize];
+
+    for voice in voices {
+        let freq = intonation.frequency(voice.index, voice.reed) as f64;
+        let first = (voice.start * SAMPLE_RATE as f64) as usize;
+        let len = (voice.duration * SAMPLE_RATE as f64) as usize;
+        for (i, sample) in samples.iter_mut().skip(first).take(len).enumerate() {
+            let t = i as f64 / SAMPLE_RATE as f64;
+            let fade = (t / FADE_SECONDS)
+                .min((voice.duration - t) / FADE_SECONDS)
+                .clamp(0.0, 1.0);
+            let value = (2.0 * std::f64::consts::PI * freq * t).sin() * fade;
+            *sample += value as f32 * voice.amplitude;
+        }
+    }
+
+    for sample in samples.iter_mut() {
+        *sample = sample.clamp(-1.0, 1.0);
+    }
+    samples
+}
+
+/// write mono samples to a 16 bit WAV file
+pub fn write_wav<P: AsRef<Path>>(path: P, samples: &[f32]) -> Result<(), hound::Error> {
+    let spec = hound::WavSpec {
+        channels: 1,
+        sample_rate: SAMPLE_RATE,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let mut writer = hound::WavWriter::create(path, spec)?;
+    for sample in samples {
+        writer.write_sample((sample * i16::MAX as f32) as i16)?;
+    }
+    writer.finalize()
+}
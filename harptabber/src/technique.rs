@@ -0,0 +1,378 @@
+use crate::transposer::TuningTable;
+use crate::{get_playback_notes, Style};
+use regex::Regex;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+use thiserror::Error;
+
+lazy_static! {
+    static ref NOTE: Regex =
+        Regex::new(r"^(?P<draw>-?)(?P<hole>\d{1,2})(?P<bend>'*)(?P<over>o?)$").unwrap();
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum TechniqueError {
+    #[error("`{0}` is not a valid technique (expected draw-bends, blow-bends, overblows, overdraws or valved, like draw-bends@2-3=2)")]
+    InvalidTechnique(String),
+}
+
+/// how deep a bend may go on every hole, in semitones
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BendLimits {
+    pub depth: u32,
+    /// depths for single holes, overriding `depth`
+    pub holes: BTreeMap<u32, u32>,
+}
+
+impl BendLimits {
+    pub fn all() -> Self {
+        Self {
+            depth: u32::MAX,
+            holes: BTreeMap::new(),
+        }
+    }
+
+    pub fn depth(&self, hole: u32) -> u32 {
+        *self.holes.get(&hole).unwrap_or(&self.depth)
+    }
+}
+
+/// holes a technique can be used on
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Holes {
+    #[default]
+    None,
+    All,
+    Some(RangeInclusive<u32>),
+}
+
+impl Holes {
+    pub fn contains(&self, hole: u32) -> bool {
+        match self {
+            Holes::None => false,
+            Holes::All => true,
+            Holes::Some(holes) => holes.contains(&hole),
+        }
+    }
+}
+
+/// the techniques a player can use
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TechniqueProfile {
+    pub draw_bends: BendLimits,
+    pub blow_bends: BendLimits,
+    pub overblows: Holes,
+    pub overdraws: Holes,
+    /// a half-valved harp, playing the notes of overblows and overdraws as valved bends
+    pub valved_bends: bool,
+}
+
+impl TechniqueProfile {
+    /// unbent notes only
+    pub fn none() -> Self {
+        Self::default()
+    }
+
+    /// any bend, but no overblows or overdraws
+    pub fn bends() -> Self {
+        Self {
+            draw_bends: BendLimits::all(),
+            blow_bends: BendLimits::all(),
+            ..Self::default()
+        }
+    }
+
+    pub fn all() -> Self {
+        Self {
+            overblows: Holes::All,
+            overdraws: Holes::All,
+            ..Self::bends()
+        }
+    }
+
+    /// whether a note in the default style can be played
+    pub fn allows(&self, note: &str) -> bool {
+        let Some(caps) = NOTE.captures(note) else {
+            return false;
+        };
+        let draw = !caps["draw"].is_empty();
+        let hole = caps["hole"].parse::<u32>().unwrap();
+        let bend = caps["bend"].len() as u32;
+
+        if !caps["over"].is_empty() {
+            let holes = if draw {
+                &self.overdraws
+            } else {
+                &self.overblows
+            };
+            return self.valved_bends || holes.contains(hole);
+        }
+        let limits = if draw {
+            &self.draw_bends
+        } else {
+            &self.blow_bends
+        };
+        bend <= limits.depth(hole)
+    }
+
+    /// whether every note of a tab for a harp in `tuning` can be played
+    pub fn allows_tab(&self, tab: &str, tuning: &str, style: Style) -> bool {
+        played_notes(tab, tuning, style)
+            .iter()
+            .all(|note| note.as_ref().is_some_and(|note| self.allows(note)))
+    }
+}
+
+/// every note and chord hole a tab plays, in the default style. `None` for notes not on the harp
+fn played_notes(tab: &str, tuning: &str, style: Style) -> Vec<Option<String>> {
+    let notes = &TuningTable::get(tuning, Style::Default).notes;
+    get_playback_notes(tab, tuning, style)
+        .iter()
+        .flat_map(|note| std::iter::once(note.index).chain(note.chord.iter().copied()))
+        .map(|index| {
+            usize::try_from(index + 9)
+                .ok()
+                .and_then(|i| notes.get(i))
+                .cloned()
+        })
+        .collect()
+}
+
+/// the techniques a tab needs on one harp
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct TechniqueInventory {
+    /// bent notes in the default style and how often they're played, lowest first
+    pub bends: Vec<(String, usize)>,
+    /// overblows and overdraws and how often they're played, lowest first
+    pub overblows: Vec<(String, usize)>,
+    /// in semitones
+    pub deepest_bend: u32,
+    pub lowest: Option<String>,
+    pub highest: Option<String>,
+    /// semitones from the lowest to the highest note
+    pub range: u32,
+}
+
+impl TechniqueInventory {
+    pub fn new(tab: &str, tuning: &str, style: Style) -> Self {
+        let notes = &TuningTable::get(tuning, Style::Default).notes;
+        let mut counts: BTreeMap<usize, usize> = BTreeMap::new();
+        for note in get_playback_notes(tab, tuning, style) {
+            for index in std::iter::once(note.index).chain(note.chord) {
+                if let Some(position) = usize::try_from(index + 9).ok().filter(|&p| p < notes.len())
+                {
+                    *counts.entry(position).or_insert(0) += 1;
+                }
+            }
+        }
+
+        let mut inventory = TechniqueInventory::default();
+        for (&position, &count) in counts.iter() {
+            let note = &notes[position];
+            let Some(caps) = NOTE.captures(note) else {
+                continue;
+            };
+            if !caps["over"].is_empty() {
+                inventory.overblows.push((note.clone(), count));
+            } else if !caps["bend"].is_empty() {
+                inventory.bends.push((note.clone(), count));
+                inventory.deepest_bend = inventory.deepest_bend.max(caps["bend"].len() as u32);
+            }
+        }
+        let lowest = counts.keys().next();
+        let highest = counts.keys().next_back();
+        inventory.lowest = lowest.map(|&p| notes[p].clone());
+        inventory.highest = highest.map(|&p| notes[p].clone());
+        inventory.range = lowest.zip(highest).map_or(0, |(l, h)| (h - l) as u32);
+        inventory
+    }
+
+    /// a line for every technique used, and one for the range
+    pub fn report(&self) -> String {
+        let list = |notes: &[(String, usize)]| {
+            let notes: Vec<String> = notes
+                .iter()
+                .map(|(note, count)| format!("{} x{}", note, count))
+                .collect();
+            notes.join(", ")
+        };
+
+        let mut res = String::new();
+        if self.bends.is_empty() {
+            res.push_str("no bends\n");
+        } else {
+            res.push_str(&format!(
+                "bends: {} (up to {} semitones)\n",
+                list(&self.bends),
+                self.deepest_bend
+            ));
+        }
+        if !self.overblows.is_empty() {
+            res.push_str(&format!("overblows: {}\n", list(&self.overblows)));
+        }
+        if let (Some(lowest), Some(highest)) = (&self.lowest, &self.highest) {
+            res.push_str(&format!(
+                "range: {} to {}, {} semitones\n",
+                lowest, highest, self.range
+            ));
+        }
+        res
+    }
+}
+
+/// how hard a note (in the default style) is to play: bends cost their depth,
+/// doubled for blow bends, overblows and overdraws cost 4
+pub fn note_difficulty(note: &str) -> u32 {
+    let Some(caps) = NOTE.captures(note) else {
+        return 0;
+    };
+    let bend = caps["bend"].len() as u32;
+    if !caps["over"].is_empty() {
+        4
+    } else if caps["draw"].is_empty() {
+        2 * bend
+    } else {
+        bend
+    }
+}
+
+/// the difficulty of all notes of a tab for a harp in `tuning`
+pub fn tab_difficulty(tab: &str, tuning: &str, style: Style) -> u32 {
+    played_notes(tab, tuning, style)
+        .iter()
+        .flatten()
+        .map(|note| note_difficulty(note))
+        .sum()
+}
+
+impl FromStr for TechniqueProfile {
+    type Err = TechniqueError;
+
+    /// `none`, `bends` or `all`, or a comma separated list of techniques like
+    /// `draw-bends=1,draw-bends@2-3=2,overblows@4-6,valved`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        lazy_static! {
+            static ref ITEM: Regex = Regex::new(
+                r"^(?P<name>[a-z-]+)(@(?P<low>\d{1,2})(-(?P<high>\d{1,2}))?)?(=(?P<depth>\d))?$"
+            )
+            .unwrap();
+        }
+
+        match s {
+            "none" => return Ok(Self::none()),
+            "bends" => return Ok(Self::bends()),
+            "all" => return Ok(Self::all()),
+            _ => (),
+        }
+
+        let mut profile = Self::none();
+        for item in s.split(',').map(str::trim) {
+            let invalid = || TechniqueError::InvalidTechnique(item.to_string());
+            let caps = ITEM.captures(item).ok_or_else(invalid)?;
+            let holes = caps.name("low").map(|low| {
+                let low = low.as_str().parse::<u32>().unwrap();
+                let high = caps
+                    .name("high")
+                    .map_or(low, |high| high.as_str().parse::<u32>().unwrap());
+                low..=high
+            });
+            let depth = caps
+                .name("depth")
+                .map(|depth| depth.as_str().parse::<u32>().unwrap());
+
+            match &caps["name"] {
+                name @ ("draw-bends" | "blow-bends") => {
+                    let limits = if name == "draw-bends" {
+                        &mut profile.draw_bends
+                    } else {
+                        &mut profile.blow_bends
+                    };
+                    let depth = depth.unwrap_or(u32::MAX);
+                    match holes {
+                        Some(holes) => limits.holes.extend(holes.map(|hole| (hole, depth))),
+                        None => limits.depth = depth,
+                    }
+                }
+                name @ ("overblows" | "overdraws") if depth.is_none() => {
+                    let allowed = holes.map_or(Holes::All, Holes::Some);
+                    if name == "overblows" {
+                        profile.overblows = allowed;
+                    } else {
+                        profile.overdraws = allowed;
+                    }
+                }
+                "valved" if holes.is_none() && depth.is_none() => profile.valved_bends = true,
+                _ => return Err(invalid()),
+            }
+        }
+        Ok(profile)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_technique_profile() {
+        let profile = TechniqueProfile::from_str("draw-bends=1,overblows@4-6").unwrap();
+        assert!(profile.allows("-3'"));
+        assert!(!profile.allows("-3''"));
+        assert!(!profile.allows("10'"));
+        assert!(profile.allows("5o"));
+        assert!(!profile.allows("1o"));
+        assert!(!profile.allows("-7o"));
+
+        let profile = TechniqueProfile::from_str("draw-bends=1,draw-bends@3=3").unwrap();
+        assert!(profile.allows("-3'''"));
+        assert!(!profile.allows("-2''"));
+
+        let valved = TechniqueProfile::from_str("valved").unwrap();
+        assert!(valved.allows("-7o"));
+        assert!(!valved.allows("-2'"));
+
+        assert_eq!(
+            TechniqueProfile::from_str("bends"),
+            Ok(TechniqueProfile::bends())
+        );
+        assert!(TechniqueProfile::from_str("overblows=2").is_err());
+        assert!(TechniqueProfile::from_str("tongue-blocking").is_err());
+    }
+
+    #[test]
+    fn test_allows_tab() {
+        let profile = TechniqueProfile::from_str("draw-bends=1").unwrap();
+        assert!(profile.allows_tab("4 -4' (4 5)", "richter", Style::Default));
+        assert!(!profile.allows_tab("-2''", "richter", Style::Default));
+        assert!(!profile.allows_tab("-2bb", "richter", Style::BBends));
+    }
+
+    #[test]
+    fn test_technique_inventory() {
+        let inventory = TechniqueInventory::new("-2'' -3' 4 -2'' 6o -1", "richter", Style::Default);
+        assert_eq!(
+            inventory.bends,
+            vec![(String::from("-2''"), 2), (String::from("-3'"), 1)]
+        );
+        assert_eq!(inventory.overblows, vec![(String::from("6o"), 1)]);
+        assert_eq!(inventory.deepest_bend, 2);
+        assert_eq!(inventory.lowest.as_deref(), Some("-1"));
+        assert_eq!(inventory.highest.as_deref(), Some("6o"));
+        assert_eq!(inventory.range, 20);
+        assert_eq!(
+            inventory.report(),
+            "bends: -2'' x2, -3' x1 (up to 2 semitones)\noverblows: 6o x1\nrange: -1 to 6o, 20 semitones\n"
+        );
+    }
+
+    #[test]
+    fn test_difficulty() {
+        assert_eq!(note_difficulty("-4"), 0);
+        assert_eq!(note_difficulty("-3''"), 2);
+        assert_eq!(note_difficulty("10'"), 2);
+        assert_eq!(note_difficulty("6o"), 4);
+        assert_eq!(tab_difficulty("-2'' 4 -3'", "richter", Style::Default), 3);
+    }
+}
@@ -0,0 +1,258 @@
+use crate::key_to_index_offset;
+use std::collections::HashMap;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// deviation from equal temperament in cents for every semitone above the tonic (5-limit just intonation)
+const JUST_CENTS: [f32; 12] = [
+    0.0, 11.73, 3.91, 15.64, -13.69, -1.96, -9.78, 1.96, 13.69, -15.64, -3.91, -11.73,
+];
+
+/// minor seventh as the harmonic seventh (7/4)
+const HARMONIC_SEVENTH_CENTS: f32 = -31.17;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum TemperamentError {
+    #[error("`{0}` is not a valid temperament")]
+    InvalidTemperament(String),
+    #[error("line {line}: `{text}` is not a valid reed offset (expected `<hole> <cents>`)")]
+    InvalidOffset { line: usize, text: String },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Temperament {
+    Equal,
+    /// 5-limit just intonation relative to the harp key
+    Just,
+    /// just intonation with a 7/4 minor seventh
+    SevenLimit,
+    /// halfway between equal temperament and just intonation, like most factory harps
+    Compromise,
+}
+
+impl Temperament {
+    /// deviation from equal temperament in cents, given the semitones above the harp key
+    pub fn cents(&self, degree: i32) -> f32 {
+        let degree = degree.rem_euclid(12) as usize;
+        match self {
+            Temperament::Equal => 0.0,
+            Temperament::Just => JUST_CENTS[degree],
+            Temperament::SevenLimit if degree == 10 => HARMONIC_SEVENTH_CENTS,
+            Temperament::SevenLimit => JUST_CENTS[degree],
+            Temperament::Compromise => JUST_CENTS[degree] / 2.0,
+        }
+    }
+}
+
+impl FromStr for Temperament {
+    type Err = TemperamentError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "equal" => Ok(Temperament::Equal),
+            "just" => Ok(Temperament::Just),
+            "7-limit" | "seven-limit" => Ok(Temperament::SevenLimit),
+            "compromise" => Ok(Temperament::Compromise),
+            _ => Err(TemperamentError::InvalidTemperament(s.to_string())),
+        }
+    }
+}
+
+/// a single reed of a harp. bends are played on the reed of the hole and direction they're
+/// written with, as are overblows and overdraws
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Reed {
+    pub hole: u32,
+    pub draw: bool,
+}
+
+impl Reed {
+    /// the reed of a note in the default style, like `-3''` or `6o`
+    pub fn of(note: &str) -> Option<Self> {
+        let (draw, hole) = match note.strip_prefix('-') {
+            Some(hole) => (true, hole),
+            None => (false, note),
+        };
+        let digits: String = hole.chars().take_while(|c| c.is_ascii_digit()).collect();
+        let hole = digits.parse().ok()?;
+        Some(Reed { hole, draw })
+    }
+}
+
+/// measured cent offsets of single reeds, optionally grouped by tuning
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReedOffsets {
+    /// (tuning, reed, cents), with `None` applying to every tuning
+    offsets: Vec<(Option<String>, Reed, f32)>,
+}
+
+impl ReedOffsets {
+    /// parse lines like `-2 -4.5`, with unbent holes written in the default style.
+    /// a `[tuning]` line makes the following offsets apply to that tuning only,
+    /// `#` starts a comment
+    pub fn parse(text: &str) -> Result<Self, TemperamentError> {
+        let mut offsets = Vec::new();
+        let mut tuning = None;
+        for (i, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                tuning = Some(name.trim().to_string());
+                continue;
+            }
+
+            let invalid = || TemperamentError::InvalidOffset {
+                line: i + 1,
+                text: line.to_string(),
+            };
+            let mut words = line.split_whitespace();
+            let (Some(hole), Some(cents), None) = (words.next(), words.next(), words.next()) else {
+                return Err(invalid());
+            };
+            let digits = hole.strip_prefix('-').unwrap_or(hole);
+            let reed = Reed::of(hole)
+                .filter(|_| digits.bytes().all(|b| b.is_ascii_digit()))
+                .ok_or_else(invalid)?;
+            let cents = cents.parse::<f32>().map_err(|_| invalid())?;
+            offsets.push((tuning.clone(), reed, cents));
+        }
+        Ok(Self { offsets })
+    }
+
+    /// offsets by reed for a harp in `tuning`
+    pub fn reeds(&self, tuning: &str) -> HashMap<Reed, f32> {
+        self.offsets
+            .iter()
+            .filter(|(t, _, _)| t.as_deref().is_none_or(|t| t == tuning))
+            .map(|(_, reed, cents)| (*reed, *cents))
+            .collect()
+    }
+}
+
+/// how note indices are turned into frequencies
+#[derive(Debug, Clone, PartialEq)]
+pub struct Intonation {
+    pub temperament: Temperament,
+    /// frequency of A4 in Hz
+    pub reference_pitch: f32,
+    /// semitones from C to the harp key, as the temperament is relative to it
+    pub key_offset: i32,
+    /// extra cents by reed, also applied to its bends
+    pub reed_offsets: HashMap<Reed, f32>,
+}
+
+impl Default for Intonation {
+    fn default() -> Self {
+        Self {
+            temperament: Temperament::Equal,
+            reference_pitch: 440.0,
+            key_offset: 0,
+            reed_offsets: HashMap::new(),
+        }
+    }
+}
+
+impl Intonation {
+    /// intonation for a harp in `key`, with reed offsets for `tuning`
+    pub fn new(
+        temperament: Temperament,
+        reference_pitch: f32,
+        key: &str,
+        tuning: &str,
+        reed_offsets: &ReedOffsets,
+    ) -> Self {
+        Self {
+            temperament,
+            reference_pitch,
+            key_offset: key_to_index_offset(key),
+            reed_offsets: reed_offsets.reeds(tuning),
+        }
+    }
+
+    /// deviation from equal temperament at A440 in cents, of a note played on `reed`
+    pub fn cents(&self, index: i32, reed: Option<Reed>) -> f32 {
+        // index 0 is A, 9 semitones above C
+        let degree = index + 9 - self.key_offset;
+        let reed = reed
+            .and_then(|reed| self.reed_offsets.get(&reed))
+            .copied()
+            .unwrap_or(0.0);
+        self.temperament.cents(degree) + reed
+    }
+
+    /// frequency in Hz of a note index (with 0 = A4) played on `reed`
+    pub fn frequency(&self, index: i32, reed: Option<Reed>) -> f32 {
+        let semitones = index as f32 + self.cents(index, reed) / 100.0;
+        f32::powf(2.0, semitones / 12.0) * self.reference_pitch
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_temperament() {
+        let just = Intonation {
+            temperament: Temperament::Just,
+            ..Intonation::default()
+        };
+        // E is the major third of C
+        assert!((just.cents(-5, None) - -13.69).abs() < 0.01);
+        assert_eq!(just.cents(-9, None), 0.0);
+        assert_eq!(Intonation::default().frequency(12, None), 880.0);
+
+        let g_harp = Intonation {
+            temperament: Temperament::SevenLimit,
+            reference_pitch: 442.0,
+            key_offset: key_to_index_offset("G"),
+            ..Intonation::default()
+        };
+        // F is the minor seventh of G
+        assert_eq!(g_harp.cents(-4, None), HARMONIC_SEVENTH_CENTS);
+        assert!((g_harp.frequency(-2, None) - 442.0 * f32::powf(2.0, -2.0 / 12.0)).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_reed_offsets() {
+        let text = "# measured\n4 +3\n3 +2\n[richter]\n-2 -5.5\n[paddy]\n-2 7\n";
+        let offsets = ReedOffsets::parse(text).unwrap();
+        let draw_2 = Reed {
+            hole: 2,
+            draw: true,
+        };
+        let blow_3 = Reed {
+            hole: 3,
+            draw: false,
+        };
+        let reeds = offsets.reeds("richter");
+        assert_eq!(reeds.len(), 3);
+        assert_eq!(
+            reeds[&Reed {
+                hole: 4,
+                draw: false
+            }],
+            3.0
+        );
+        assert_eq!(reeds[&draw_2], -5.5);
+
+        // 2 draw and 3 blow are both G, but tuned apart
+        let intonation = Intonation::new(Temperament::Equal, 440.0, "C", "richter", &offsets);
+        assert_eq!(intonation.cents(-2, Some(draw_2)), -5.5);
+        assert_eq!(intonation.cents(-2, Some(blow_3)), 2.0);
+        // bends are played on the reed they bend
+        assert_eq!(Reed::of("-2''"), Some(draw_2));
+        assert_eq!(intonation.cents(-4, Reed::of("-2''")), -5.5);
+
+        assert_eq!(
+            ReedOffsets::parse("4 +3\n5"),
+            Err(TemperamentError::InvalidOffset {
+                line: 2,
+                text: "5".to_string()
+            })
+        );
+        assert!(ReedOffsets::parse("-3' 4").is_err());
+    }
+}
@@ -0,0 +1,264 @@
+use crate::{change_tab_style, key_to_index_offset, tuning_to_notes_in_order, Style};
+use std::path::Path;
+use thiserror::Error;
+
+/// samples per analysis frame, and samples between the starts of frames
+const FRAME_SIZE: usize = 2048;
+const HOP_SIZE: usize = 512;
+
+/// frequency range searched for the fundamental, covering low F to high G harps
+const MIN_FREQUENCY: f32 = 80.0;
+const MAX_FREQUENCY: f32 = 2500.0;
+
+/// YIN threshold on the normalized difference, lower is stricter
+const YIN_THRESHOLD: f32 = 0.15;
+
+/// frames quieter than this (RMS) count as silence
+const SILENCE_RMS: f32 = 0.01;
+
+/// shorter pitch runs are dropped, these are mostly note transitions
+const MIN_NOTE_FRAMES: usize = 3;
+
+#[derive(Error, Debug)]
+pub enum TranscriptionError {
+    #[error("could not read wav file: {0}")]
+    Wav(#[from] hound::Error),
+    #[error("expected a mono wav file, got {0} channels")]
+    NotMono(u16),
+}
+
+/// a note found in a recording
+#[derive(Debug, Clone, PartialEq)]
+pub struct TranscribedNote {
+    /// note index (with 0 = A440)
+    pub index: i32,
+    /// `None` if the note can't be played on the harp
+    pub tab: Option<String>,
+    /// start time in seconds
+    pub start: f64,
+    /// length in seconds
+    pub duration: f64,
+    /// how periodic the signal was, from 0 to 1
+    pub confidence: f32,
+    /// average deviation from the equal tempered pitch
+    pub cents: f32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Transcription {
+    pub notes: Vec<TranscribedNote>,
+}
+
+impl Transcription {
+    /// the transcribed tab, with unplayable notes written as `X`
+    pub fn tab(&self) -> String {
+        let holes: Vec<&str> = self
+            .notes
+            .iter()
+            .map(|note| note.tab.as_deref().unwrap_or("X"))
+            .collect();
+        holes.join(" ") + "\n"
+    }
+
+    /// one line per note with its timing, confidence and tuning
+    pub fn report(&self) -> String {
+        self.notes
+            .iter()
+            .map(|note| {
+                format!(
+                    "{:>5} {:6.2}s {:5.2}s {:3.0}% {:+4.0} cents\n",
+                    note.tab.as_deref().unwrap_or("X"),
+                    note.start,
+                    note.duration,
+                    note.confidence * 100.0,
+                    note.cents
+                )
+            })
+            .collect()
+    }
+}
+
+/// read a mono wav file as samples between -1 and 1, along with its sample rate
+pub fn read_wav<P: AsRef<Path>>(path: P) -> Result<(Vec<f32>, u32), TranscriptionError> {
+    let mut reader = hound::WavReader::open(path)?;
+    let spec = reader.spec();
+    if spec.channels != 1 {
+        return Err(TranscriptionError::NotMono(spec.channels));
+    }
+    let samples = match spec.sample_format {
+        hound::SampleFormat::Float => reader.samples::<f32>().collect::<Result<Vec<_>, _>>()?,
+        hound::SampleFormat::Int => {
+            let max = (1_i64 << (spec.bits_per_sample - 1)) as f32;
+            reader
+                .samples::<i32>()
+                .map(|s| s.map(|s| s as f32 / max))
+                .collect::<Result<Vec<_>, _>>()?
+        }
+    };
+    Ok((samples, spec.sample_rate))
+}
+
+/// fundamental frequency of a frame using the YIN algorithm, along with a confidence from 0 to 1
+pub fn detect_pitch(frame: &[f32], sample_rate: u32) -> Option<(f32, f32)> {
+    let window = frame.len() / 2;
+    let min_tau = (sample_rate as f32 / MAX_FREQUENCY) as usize;
+    let max_tau = ((sample_rate as f32 / MIN_FREQUENCY) as usize).min(window);
+
+    // cumulative mean normalized difference function
+    let mut cmnd = vec![1.0; max_tau + 1];
+    let mut running_sum = 0.0;
+    for tau in 1..=max_tau {
+        let difference: f32 = (0..window)
+            .map(|j| (frame[j] - frame[j + tau]).powi(2))
+            .sum();
+        running_sum += difference;
+        cmnd[tau] = if running_sum > 0.0 {
+            difference * tau as f32 / running_sum
+        } else {
+            1.0
+        };
+    }
+
+    let mut tau = (min_tau.max(2)..max_tau).find(|&tau| cmnd[tau] < YIN_THRESHOLD)?;
+    while tau + 1 < max_tau && cmnd[tau + 1] < cmnd[tau] {
+        tau += 1;
+    }
+
+    // parabolic interpolation around the minimum
+    let (prev, cur, next) = (cmnd[tau - 1], cmnd[tau], cmnd[tau + 1]);
+    let denominator = prev + next - 2.0 * cur;
+    let shift = if denominator.abs() > f32::EPSILON {
+        (prev - next) / (2.0 * denominator)
+    } else {
+        0.0
+    };
+
+    let frequency = sample_rate as f32 / (tau as f32 + shift);
+    Some((frequency, (1.0 - cur).clamp(0.0, 1.0)))
+}
+
+/// note index, first frame and (cents, confidence) of every frame of a detected note
+type Segment = (i32, usize, Vec<(f32, f32)>);
+
+/// find the notes in a monophonic recording, written as tab for a harp in `tuning` and `key`
+pub fn transcribe(
+    samples: &[f32],
+    sample_rate: u32,
+    tuning: &str,
+    key: &str,
+    style: Style,
+) -> Transcription {
+    let (notes, _) = tuning_to_notes_in_order(tuning);
+    let notes = change_tab_style(&notes, style);
+    let key_offset = key_to_index_offset(key);
+    let to_tab = |index: i32| {
+        usize::try_from(index + 9 - key_offset)
+            .ok()
+            .and_then(|i| notes.get(i))
+            .filter(|note| *note != "X")
+            .cloned()
+    };
+
+    let mut current: Option<Segment> = None;
+    let mut transcription = Transcription::default();
+    let mut finish = |current: Option<Segment>| {
+        let Some((index, first, frames)) = current else {
+            return;
+        };
+        if frames.len() < MIN_NOTE_FRAMES {
+            return;
+        }
+        let count = frames.len() as f32;
+        transcription.notes.push(TranscribedNote {
+            index,
+            tab: to_tab(index),
+            start: (first * HOP_SIZE) as f64 / sample_rate as f64,
+            duration: (frames.len() * HOP_SIZE) as f64 / sample_rate as f64,
+            confidence: frames.iter().map(|(_, c)| c).sum::<f32>() / count,
+            cents: frames.iter().map(|(cents, _)| cents).sum::<f32>() / count,
+        });
+    };
+
+    let frame_count = samples.len().saturating_sub(FRAME_SIZE) / HOP_SIZE;
+    for i in 0..frame_count {
+        let frame = &samples[i * HOP_SIZE..i * HOP_SIZE + FRAME_SIZE];
+        let rms = (frame.iter().map(|s| s * s).sum::<f32>() / FRAME_SIZE as f32).sqrt();
+        let pitch = (rms >= SILENCE_RMS)
+            .then(|| detect_pitch(frame, sample_rate))
+            .flatten();
+
+        let Some((frequency, confidence)) = pitch else {
+            finish(current.take());
+            continue;
+        };
+        let semitones = 12.0 * (frequency / 440.0).log2();
+        let index = semitones.round() as i32;
+        let cents = (semitones - index as f32) * 100.0;
+
+        match current.as_mut() {
+            Some((current_index, _, frames)) if *current_index == index => {
+                frames.push((cents, confidence));
+            }
+            _ => {
+                finish(current.take());
+                current = Some((index, i, vec![(cents, confidence)]));
+            }
+        }
+    }
+    finish(current);
+
+    transcription
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::synth::{render, write_wav, Voice, SAMPLE_RATE};
+
+    #[test]
+    fn test_detect_pitch() {
+        let voice = Voice {
+            index: 3,
+            reed: None,
+            start: 0.0,
+            duration: 0.1,
+            amplitude: 0.2,
+        };
+        let samples = render(&[voice]);
+        let (frequency, confidence) = detect_pitch(&samples[..FRAME_SIZE], SAMPLE_RATE).unwrap();
+        assert!((frequency - 523.25).abs() < 1.0);
+        assert!(confidence > 0.9);
+
+        let silence = vec![0.0; FRAME_SIZE];
+        assert_eq!(detect_pitch(&silence, SAMPLE_RATE), None);
+    }
+
+    #[test]
+    fn test_transcribe_wav() {
+        // 4 -4 5 -5 on a C harp, with a short rest before the last note
+        let voices: Vec<Voice> = [(3, 0.0), (5, 0.3), (7, 0.6), (8, 1.0)]
+            .iter()
+            .map(|&(index, start)| Voice {
+                index,
+                reed: None,
+                start,
+                duration: 0.3,
+                amplitude: 0.2,
+            })
+            .collect();
+        let path = std::env::temp_dir().join("harptabber_test_transcribe.wav");
+        write_wav(&path, &render(&voices)).unwrap();
+
+        let (samples, sample_rate) = read_wav(&path).unwrap();
+        let transcription = transcribe(&samples, sample_rate, "richter", "C", Style::Default);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(transcription.tab(), "4 -4 5 -5\n");
+        assert!(transcription.notes.iter().all(|n| n.confidence > 0.9));
+        assert!(transcription.notes.iter().all(|n| n.cents.abs() < 5.0));
+        assert!((transcription.notes[3].start - 1.0).abs() < 0.05);
+
+        let g_harp = transcribe(&samples, sample_rate, "richter", "G", Style::Default);
+        assert_eq!(g_harp.tab(), "-5 6 -6 6o\n");
+    }
+}
@@ -0,0 +1,387 @@
+use crate::hole_group::{self, HoleGroup};
+use crate::ornament::{OrnamentedNote, ARTICULATION_WORDS};
+use crate::{
+    change_tab_style, semitones_to_position, tuning_to_notes_in_order, Style, Tab, TechniqueProfile,
+};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::{Arc, Mutex};
+
+/// range of shifts searched for playable positions
+const SEARCHED_SEMITONES: std::ops::RangeInclusive<i32> = -24..=24;
+
+/// the notes of a tuning in one style, with their positions for quick lookup
+#[derive(Debug)]
+pub(crate) struct TuningTable {
+    pub(crate) notes: Vec<String>,
+    pub(crate) duplicated_notes: Vec<String>,
+    pub(crate) default_notes: Vec<String>,
+    pub(crate) default_duplicated_notes: Vec<String>,
+    /// the position of every note, duplicated notes included
+    positions: HashMap<String, i32>,
+}
+
+impl TuningTable {
+    /// the table of a tuning in a style, built once and then cached
+    pub(crate) fn get(tuning: &str, style: Style) -> Arc<TuningTable> {
+        lazy_static! {
+            static ref TABLES: Mutex<HashMap<(String, Style), Arc<TuningTable>>> =
+                Mutex::new(HashMap::new());
+        }
+        let mut tables = TABLES.lock().unwrap();
+        tables
+            .entry((tuning.to_string(), style))
+            .or_insert_with(|| Arc::new(TuningTable::new(tuning, style)))
+            .clone()
+    }
+
+    fn new(tuning: &str, style: Style) -> Self {
+        let (default_notes, default_duplicated_notes) = tuning_to_notes_in_order(tuning);
+        let notes = change_tab_style(&default_notes, style);
+        let duplicated_notes = change_tab_style(&default_duplicated_notes, style);
+
+        let mut positions = HashMap::new();
+        for (i, note) in notes.iter().enumerate() {
+            positions.entry(note.clone()).or_insert(i as i32);
+        }
+        for pair in duplicated_notes.chunks(2) {
+            if let [duplicate, note] = pair {
+                if let Some(&position) = positions.get(note) {
+                    positions.insert(duplicate.clone(), position);
+                }
+            }
+        }
+
+        TuningTable {
+            notes,
+            duplicated_notes,
+            default_notes,
+            default_duplicated_notes,
+            positions,
+        }
+    }
+}
+
+/// a token of the input tab
+#[derive(Debug)]
+enum Token {
+    /// a single note, by its position in the input notes
+    Note(i32),
+    Group(HoleGroup),
+    Ornamented(OrnamentedNote),
+    /// articulation words, kept as they are
+    Word(String),
+    Invalid(String),
+}
+
+/// a token of the input along with where it is
+#[derive(Debug)]
+struct Spanned {
+    /// byte range in the tab
+    span: Range<usize>,
+    text: String,
+    token: Token,
+    /// positions in the input notes of the notes a group or ornament plays
+    positions: Vec<i32>,
+}
+
+/// a token of the input and what it became
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TransposedToken {
+    pub input: String,
+    /// `None` for tokens that aren't notes on the input harp
+    pub output: Option<String>,
+    /// line in the tab, starting at 1
+    pub line: usize,
+    /// byte range in the tab
+    pub span: Range<usize>,
+}
+
+/// a tab parsed once, to be transposed by any number of semitones.
+/// tuning tables are cached, so preparing the same tunings again is cheap
+#[derive(Debug)]
+pub struct Transposer {
+    style: Style,
+    input: Arc<TuningTable>,
+    output: Arc<TuningTable>,
+    lines: Vec<Vec<Spanned>>,
+}
+
+impl Transposer {
+    pub fn new(tab: &str, style: Style, input_tuning: &str, output_tuning: &str) -> Self {
+        let input = TuningTable::get(input_tuning, style);
+        let output = TuningTable::get(output_tuning, style);
+
+        let mut lines = Vec::new();
+        let mut offset = 0;
+        for line in tab.split_inclusive('\n') {
+            let start = offset;
+            offset += line.len();
+            let line = line.trim_end_matches('\n').trim_end_matches('\r');
+            let tokens = hole_group::tokens(line)
+                .into_iter()
+                .map(|(span, token)| {
+                    // replace double quotes in input with two single quotes
+                    let text = token.replace('"', "''");
+                    let token = Self::parse_token(&text, &input);
+                    Spanned {
+                        span: start + span.start..start + span.end,
+                        positions: Self::played_positions(&token, &input),
+                        token,
+                        text,
+                    }
+                })
+                .collect();
+            lines.push(tokens);
+        }
+
+        Transposer {
+            style,
+            input,
+            output,
+            lines,
+        }
+    }
+
+    fn parse_token(token: &str, input: &TuningTable) -> Token {
+        if let Some(&position) = input.positions.get(token) {
+            Token::Note(position)
+        } else if let Some(group) = HoleGroup::parse(token, &input.notes, &input.duplicated_notes) {
+            Token::Group(group)
+        } else if let Some(ornamented) =
+            OrnamentedNote::parse(token, &input.notes, &input.duplicated_notes)
+        {
+            Token::Ornamented(ornamented)
+        } else if ARTICULATION_WORDS.contains(&token) {
+            Token::Word(token.to_string())
+        } else {
+            Token::Invalid(token.to_string())
+        }
+    }
+
+    /// like `get_playback_notes`, every hole of a group and the main note of an ornament
+    fn played_positions(token: &Token, input: &TuningTable) -> Vec<i32> {
+        let holes: Vec<&String> = match token {
+            Token::Group(group) => group.holes.iter().collect(),
+            Token::Ornamented(ornamented) => vec![&ornamented.note],
+            _ => return Vec::new(),
+        };
+        holes
+            .into_iter()
+            .filter_map(|hole| input.positions.get(hole).copied())
+            .collect()
+    }
+
+    /// the note at `position` in the output notes, or `X` if it isn't on the output harp
+    fn output_note(&self, position: i32) -> &str {
+        usize::try_from(position)
+            .ok()
+            .and_then(|p| self.output.notes.get(p))
+            .map_or("X", |note| note.as_str())
+    }
+
+    /// transpose a token that isn't a single note or an invalid one
+    fn transpose_other(&self, token: &Token, semitones: i32) -> String {
+        match token {
+            Token::Group(group) => group.transpose(
+                &self.input.notes,
+                &self.input.duplicated_notes,
+                &self.output.default_notes,
+                &self.output.default_duplicated_notes,
+                semitones,
+                self.style,
+            ),
+            Token::Ornamented(ornamented) => ornamented.transpose(
+                &self.input.notes,
+                &self.input.duplicated_notes,
+                &self.output.default_notes,
+                &self.output.default_duplicated_notes,
+                semitones,
+                self.style,
+            ),
+            Token::Word(word) | Token::Invalid(word) => word.clone(),
+            Token::Note(position) => self.output_note(position + semitones).to_string(),
+        }
+    }
+
+    /// the same as `transpose_tabs` on the prepared tab
+    pub fn transpose(&self, semitones: i32, keep_errors: bool) -> (String, Vec<String>) {
+        let mut errors = Vec::new();
+        let result = self
+            .lines
+            .iter()
+            .map(|line| {
+                let mut res = String::new();
+                for Spanned { token, .. } in line {
+                    match token {
+                        Token::Note(position) => {
+                            res.push_str(self.output_note(position + semitones))
+                        }
+                        Token::Invalid(token) => {
+                            errors.push(token.clone());
+                            if !keep_errors {
+                                continue;
+                            }
+                            res.push_str(token);
+                        }
+                        token => res.push_str(&self.transpose_other(token, semitones)),
+                    }
+                    res.push(' ');
+                }
+                res
+            })
+            .chain(["".to_owned()])
+            .collect::<Vec<String>>()
+            .join("\n");
+        (result, errors)
+    }
+
+    /// every token of the tab and what it became, shifting every line by the semitones `shift`
+    /// gives for its index. lines it gives `None` for are left out
+    pub fn tokens(&self, shift: impl Fn(usize) -> Option<i32>) -> Vec<TransposedToken> {
+        let mut res = Vec::new();
+        for (i, line) in self.lines.iter().enumerate() {
+            let Some(semitones) = shift(i) else {
+                continue;
+            };
+            for spanned in line {
+                let output = match &spanned.token {
+                    Token::Invalid(_) => None,
+                    token => Some(self.transpose_other(token, semitones)),
+                };
+                res.push(TransposedToken {
+                    input: spanned.text.clone(),
+                    output,
+                    line: i + 1,
+                    span: spanned.span.clone(),
+                });
+            }
+        }
+        res
+    }
+
+    /// whether every note of the output notes can be played with the techniques of a profile
+    pub(crate) fn allowed(&self, technique: &TechniqueProfile) -> Vec<bool> {
+        self.output
+            .default_notes
+            .iter()
+            .map(|note| technique.allows(note))
+            .collect()
+    }
+
+    /// whether the tab shifted by `semitones` is on the output harp and can be played with the
+    /// techniques `allowed` gives. notes are checked by their positions alone
+    pub(crate) fn is_playable(&self, semitones: i32, allowed: &[bool]) -> bool {
+        let technique_allows = |position: i32| {
+            usize::try_from(position)
+                .ok()
+                .and_then(|p| allowed.get(p))
+                .is_some_and(|&allowed| allowed)
+        };
+        self.lines
+            .iter()
+            .flatten()
+            .all(|spanned| match &spanned.token {
+                Token::Note(position) => technique_allows(position + semitones),
+                Token::Invalid(_) | Token::Word(_) => true,
+                token => {
+                    spanned
+                        .positions
+                        .iter()
+                        .all(|position| technique_allows(position + semitones))
+                        && self.transpose_other(token, semitones).is_playable()
+                }
+            })
+    }
+
+    /// find all positions the tab is playable in with the techniques of a profile
+    /// and their semitone offsets
+    pub fn playable_positions(
+        &self,
+        input_position: u32,
+        technique: &TechniqueProfile,
+    ) -> Vec<(u32, i32)> {
+        if self.lines.iter().all(|line| line.is_empty()) {
+            return Vec::new();
+        }
+        let allowed = self.allowed(technique);
+        SEARCHED_SEMITONES
+            .filter(|&semitones| self.is_playable(semitones, &allowed))
+            .map(|semitones| (semitones_to_position(input_position, semitones), semitones))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transpose_tabs;
+
+    #[test]
+    fn test_transposer() {
+        let tab = "-2 -3' 4 (4 5) -4v\n-4~-5 asdf \"-3\" gliss";
+        let transposer = Transposer::new(tab, Style::Default, "richter", "richter");
+        let (res, errors) = transposer.transpose(12, false);
+        assert_eq!(res, "6 6o 7 (7 8) -8v \n-8~-9 gliss \n");
+        assert_eq!(errors, vec!["asdf", "''-3''"]);
+        let (res, _) = transposer.transpose(-12, true);
+        assert_eq!(res, "X X 1 (1 2) -1v \n-1~-2 asdf ''-3'' gliss \n");
+    }
+
+    #[test]
+    fn test_tokens() {
+        let tab = "4 asdf\n-3\" -2";
+        let transposer = Transposer::new(tab, Style::Default, "richter", "richter");
+        let tokens = transposer.tokens(|line| (line == 0).then_some(12));
+        assert_eq!(
+            tokens,
+            vec![
+                TransposedToken {
+                    input: String::from("4"),
+                    output: Some(String::from("7")),
+                    line: 1,
+                    span: 0..1,
+                },
+                TransposedToken {
+                    input: String::from("asdf"),
+                    output: None,
+                    line: 1,
+                    span: 2..6,
+                },
+            ]
+        );
+        let tokens = transposer.tokens(|_| Some(0));
+        assert_eq!(tokens[2].span, 7..10);
+        assert_eq!(tokens[2].output, Some(String::from("-3''")));
+    }
+
+    #[test]
+    fn test_playable_positions() {
+        let tab = "-2 -3' 4 (4 5) -4v\n-4~-5 1-4 gliss 6o";
+        for technique in [
+            TechniqueProfile::none(),
+            TechniqueProfile::bends(),
+            TechniqueProfile::all(),
+        ] {
+            // the same as checking the whole transposed tab
+            let expected: Vec<(u32, i32)> = SEARCHED_SEMITONES
+                .filter(|&semitones| {
+                    let (transposed, _) = transpose_tabs(
+                        tab.to_string(),
+                        semitones,
+                        false,
+                        Style::Default,
+                        "richter",
+                        "richter",
+                    );
+                    transposed.is_playable()
+                        && technique.allows_tab(&transposed, "richter", Style::Default)
+                })
+                .map(|semitones| (semitones_to_position(2, semitones), semitones))
+                .collect();
+            let transposer = Transposer::new(tab, Style::Default, "richter", "richter");
+            assert_eq!(transposer.playable_positions(2, &technique), expected);
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use crate::playback::AudioOutput;
+use crate::synth::SAMPLE_RATE;
+use crate::temperament::{Intonation, Reed};
+use web_sys::{AudioContext, GainNode, OscillatorType};
+
+/// gain of single sine notes, which are played at full amplitude
+const NOTE_GAIN: f32 = 0.2;
+
+/// audio output using the browser's Web Audio API
+pub struct WebAudioOutput {
+    context: AudioContext,
+    // every note is routed through this node, so all of them can be silenced at once
+    master: GainNode,
+    // the same for rendered samples, which are already mixed to full scale
+    samples: GainNode,
+    intonation: Intonation,
+}
+
+impl WebAudioOutput {
+    pub fn new() -> Self {
+        let context = AudioContext::new().unwrap();
+        let master = Self::create_output(&context, NOTE_GAIN);
+        let samples = Self::create_output(&context, 1.0);
+        Self {
+            context,
+            master,
+            samples,
+            intonation: Intonation::default(),
+        }
+    }
+
+    pub fn set_intonation(&mut self, intonation: Intonation) {
+        self.intonation = intonation;
+    }
+
+    /// a gain node connected to the destination
+    fn create_output(context: &AudioContext, gain: f32) -> GainNode {
+        let output = context.create_gain().unwrap();
+        output.gain().set_value(gain);
+        output
+            .connect_with_audio_node(&context.destination())
+            .unwrap();
+        output
+    }
+}
+
+impl Default for WebAudioOutput {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AudioOutput for WebAudioOutput {
+    fn now(&self) -> f64 {
+        self.context.current_time()
+    }
+
+    fn play_note(&mut self, index: i32, reed: Option<Reed>, duration: f64) {
+        // browsers keep audio contexts suspended until the user interacts with the page
+        let _ = self.context.resume();
+
+        let freq = self.intonation.frequency(index, reed);
+        let oscillator = self.context.create_oscillator().unwrap();
+        oscillator.set_type(OscillatorType::Sine);
+        oscillator.frequency().set_value(freq);
+        oscillator.connect_with_audio_node(&self.master).unwrap();
+
+        let start = self.context.current_time();
+        oscillator.start_with_when(start).unwrap();
+        oscillator.stop_with_when(start + duration).unwrap();
+    }
+
+    fn play_samples(&mut self, samples: &[f32]) {
+        let _ = self.context.resume();
+        if samples.is_empty() {
+            return;
+        }
+
+        let buffer = self
+            .context
+            .create_buffer(1, samples.len() as u32, SAMPLE_RATE as f32)
+            .unwrap();
+        buffer.copy_to_channel(samples, 0).unwrap();
+        let source = self.context.create_buffer_source().unwrap();
+        source.set_buffer(Some(&buffer));
+        source.connect_with_audio_node(&self.samples).unwrap();
+        source.start().unwrap();
+    }
+
+    fn stop(&mut self) {
+        let _ = self.master.disconnect();
+        let _ = self.samples.disconnect();
+        self.master = Self::create_output(&self.context, NOTE_GAIN);
+        self.samples = Self::create_output(&self.context, 1.0);
+    }
+}